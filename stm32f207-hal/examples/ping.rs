@@ -1,7 +1,7 @@
 #![no_std]
 #![no_main]
 
-use cortex_m_rt::entry;
+use cortex_m_rt::{entry, exception};
 
 use cortex_m_semihosting::hprintln;
 use panic_halt as _;
@@ -9,26 +9,31 @@ use panic_halt as _;
 // Needed for linking
 #[allow(unused_imports)]
 use stm32f2::stm32f217 as _;
-use stm32f2::stm32f217::Peripherals;
+use stm32f2::stm32f217::{interrupt, Peripherals};
 use stm32f207_hal::{
-    delay::Delay,
     ethernet::{
-        device::EthernetDevice,
-        pins::MiiPins,
-        ring::{Receive, RingEntry, Transmit},
+        device::{EthernetDevice, LinkEvent},
+        interrupt as ethernet_interrupt,
+        phy::{Dp83848, Phy},
+        pins::{MdioPins, MiiPins},
+        ring::{Receive, Ring, Transmit},
     },
+    ethernet_ring,
     interrupt_free_cell::InterruptFreeCell,
     prelude::*,
+    system_clock::{self, SysTickClock},
 };
 
 use smoltcp::{
     iface::{Interface, InterfaceBuilder, NeighborCache, Routes, SocketStorage},
     phy::Device,
     socket::{Dhcpv4Event, Dhcpv4Socket, IcmpPacketMetadata, IcmpSocket, IcmpSocketBuffer},
-    time::{Duration, Instant},
     wire::{EthernetAddress, IpAddress, IpCidr, Ipv4Address, Ipv4Cidr},
 };
 
+/// A locally administered unicast address
+const MAC_ADDRESS: [u8; 6] = [0x02, 0x00, 0x00, 0x00, 0x00, 0x02];
+
 #[entry]
 fn main() -> ! {
     hprintln!("Starting...").unwrap();
@@ -66,8 +71,8 @@ fn main() -> ! {
         receive_er: gpio_i.pi10.into_alternate::<11>(),
     };
 
-    // Setup the delay
-    let mut delay = Delay::new(core_peripherals.SYST, clocks);
+    // Setup the system clock, which provides the timestamps for smoltcp
+    let _system_clock = SysTickClock::new(core_peripherals.SYST, clocks);
 
     // Setup the smoltcp interface
     let Buffers {
@@ -79,17 +84,35 @@ fn main() -> ! {
         icmp_transmit_payload_buffer,
     } = get_buffers().unwrap();
 
-    let ethernet = EthernetDevice::new(
+    let mdio_pins = MdioPins {
+        mdc: gpio_c.pc1.into_alternate::<11>(),
+        mdio: gpio_a.pa2.into_alternate::<11>(),
+    };
+
+    let mut ethernet = EthernetDevice::new(
         peripherals.ETHERNET_MAC,
         peripherals.ETHERNET_DMA,
         peripherals.ETHERNET_PTP,
-        receive_buffer.as_mut_slice(),
-        transmit_buffer.as_mut_slice(),
+        receive_buffer,
+        transmit_buffer,
+        MAC_ADDRESS,
         clocks,
         mii_pins,
     )
     .expect("Could not build device");
 
+    // Wake up the main loop, when a frame arrives
+    ethernet.listen();
+
+    // Let the PHY negotiate the link with its partner
+    let mut phy = Dp83848::new(Dp83848::EVAL_BOARD_ADDRESS);
+    {
+        let mut mdio = ethernet.mdio(&mdio_pins);
+        phy.reset(&mut mdio).expect("Could not reset the PHY");
+        phy.start_autonegotiation(&mut mdio)
+            .expect("Could not start the autonegotiation");
+    }
+
     let mut sockets: [SocketStorage; 2] = Default::default();
 
     hprintln!("Setting up the interface ethernet").unwrap();
@@ -102,7 +125,7 @@ fn main() -> ! {
     let mut interface = InterfaceBuilder::new(ethernet, sockets.as_mut_slice())
         .ip_addrs(ip_address.as_mut_slice())
         .routes(Routes::new(routes.as_mut_slice()))
-        .hardware_addr(EthernetAddress([0x02, 0x00, 0x00, 0x00, 0x00, 0x02]).into()) // What about [0x02, 0x00, 0x00, 0x00, 0x00, 0x02] ?
+        .hardware_addr(EthernetAddress(MAC_ADDRESS).into())
         .neighbor_cache(neighbor_cache)
         .finalize();
 
@@ -125,9 +148,29 @@ fn main() -> ! {
 
     hprintln!("Listening for messages!").unwrap();
 
-    let mut timestamp = Instant::from_millis(0);
+    let mut next_link_poll = 0;
 
     loop {
+        let timestamp = system_clock::now();
+
+        // Follow the link once per second, and restart DHCP for a new link
+        let link_event = if system_clock::millis() >= next_link_poll {
+            next_link_poll = system_clock::millis() + 1_000;
+            interface.device_mut().poll_link(&mut phy, &mdio_pins)
+        } else {
+            Ok(None)
+        };
+
+        match link_event {
+            Ok(Some(LinkEvent::Up(link))) => {
+                hprintln!("Link up: {:?}", link).unwrap();
+                interface.get_socket::<Dhcpv4Socket>(dhcp_handle).reset();
+            }
+            Ok(Some(LinkEvent::Down)) => hprintln!("Link down").unwrap(),
+            Ok(None) => {}
+            Err(e) => hprintln!("PHY error: {:?}", e).unwrap(),
+        }
+
         if let Err(e) = interface.poll(timestamp) {
             hprintln!("poll error: {:?}", e).unwrap();
         };
@@ -135,16 +178,30 @@ fn main() -> ! {
         // Handle DHCP Messages
         handle_dhcp_messages(&mut interface, dhcp_handle);
 
-        // Determine how long to wait before the next poll
-        const DURATION_1US: Duration = Duration::from_micros(1);
-        let delay_duration = interface.poll_delay(timestamp).unwrap_or(DURATION_1US);
+        // wait for the recommended amount of time, or until a frame arrives,
+        // the SysTick interrupt wakes the core every millisecond
+        if let Some(delay_duration) = interface.poll_delay(timestamp) {
+            let next_poll = timestamp + delay_duration;
 
-        // wait for the recommended amount of time
-        timestamp += delay_duration;
-        delay.delay_us(delay_duration.micros() as u32);
+            while system_clock::now() < next_poll {
+                if ethernet_interrupt::sleep() {
+                    break;
+                }
+            }
+        }
     }
 }
 
+#[exception]
+fn SysTick() {
+    system_clock::tick();
+}
+
+#[interrupt]
+fn ETH() {
+    ethernet_interrupt::on_interrupt();
+}
+
 fn handle_dhcp_messages<'r, 't>(
     interface: &mut Interface<EthernetDevice<'r, 't>>,
     dhcp_handle: smoltcp::iface::SocketHandle,
@@ -187,8 +244,8 @@ where
 }
 
 struct Buffers {
-    receive_buffer: &'static mut [RingEntry<Receive>; 16],
-    transmit_buffer: &'static mut [RingEntry<Transmit>; 16],
+    receive_buffer: Ring<'static, Receive>,
+    transmit_buffer: Ring<'static, Transmit>,
     icmp_receive_metadata_buffer: &'static mut [IcmpPacketMetadata; 16],
     icmp_transmit_metadata_buffer: &'static mut [IcmpPacketMetadata; 16],
     icmp_receive_payload_buffer: &'static mut [u8; 1024],
@@ -204,43 +261,10 @@ fn get_buffers() -> Option<Buffers> {
         return None;
     }
 
-    // Im not aware how this repetition can be avoid
-    static mut RECEIVE_BUFFER: [RingEntry<Receive>; 16] = [
-        RingEntry::new_receive(),
-        RingEntry::new_receive(),
-        RingEntry::new_receive(),
-        RingEntry::new_receive(),
-        RingEntry::new_receive(),
-        RingEntry::new_receive(),
-        RingEntry::new_receive(),
-        RingEntry::new_receive(),
-        RingEntry::new_receive(),
-        RingEntry::new_receive(),
-        RingEntry::new_receive(),
-        RingEntry::new_receive(),
-        RingEntry::new_receive(),
-        RingEntry::new_receive(),
-        RingEntry::new_receive(),
-        RingEntry::new_receive(),
-    ];
-    static mut TRANSMIT_BUFFER: [RingEntry<Transmit>; 16] = [
-        RingEntry::new_transmit(),
-        RingEntry::new_transmit(),
-        RingEntry::new_transmit(),
-        RingEntry::new_transmit(),
-        RingEntry::new_transmit(),
-        RingEntry::new_transmit(),
-        RingEntry::new_transmit(),
-        RingEntry::new_transmit(),
-        RingEntry::new_transmit(),
-        RingEntry::new_transmit(),
-        RingEntry::new_transmit(),
-        RingEntry::new_transmit(),
-        RingEntry::new_transmit(),
-        RingEntry::new_transmit(),
-        RingEntry::new_transmit(),
-        RingEntry::new_transmit(),
-    ];
+    // The Ethernet rings are handed out once by the macro
+    let receive_buffer = ethernet_ring!(Receive, 16)?;
+    let transmit_buffer = ethernet_ring!(Transmit, 16)?;
+
     static mut ICMP_RECEIVE_METADATA_BUFFER: [IcmpPacketMetadata; 16] =
         [IcmpPacketMetadata::EMPTY; 16];
     static mut ICMP_TRANSMIT_METADATA_BUFFER: [IcmpPacketMetadata; 16] =
@@ -254,8 +278,8 @@ fn get_buffers() -> Option<Buffers> {
     // We need this unsafe, to avoid the buffers being allocated on the stack
     unsafe {
         Some(Buffers {
-            receive_buffer: &mut RECEIVE_BUFFER,
-            transmit_buffer: &mut TRANSMIT_BUFFER,
+            receive_buffer,
+            transmit_buffer,
             icmp_receive_metadata_buffer: &mut ICMP_RECEIVE_METADATA_BUFFER,
             icmp_transmit_metadata_buffer: &mut ICMP_TRANSMIT_METADATA_BUFFER,
             icmp_receive_payload_buffer: &mut ICMP_RECEIVE_PAYLOAD_BUFFER,
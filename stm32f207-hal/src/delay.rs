@@ -1,9 +1,14 @@
-use crate::rcc::{Clocks, HSI};
+use crate::rcc::Clocks;
+use crate::time::{Duration, Period};
+use crate::timer::Timer;
 use cortex_m::asm;
 use cortex_m::peripheral::syst::SystClkSource;
 use cortex_m::peripheral::SYST;
 use embedded_hal::blocking::delay::{DelayMs, DelayUs};
+use embedded_hal::timer::CountDown;
+use nb::block;
 
+/// Delay provider based on SysTick
 pub struct Delay {
     clocks: Clocks,
     syst: SYST,
@@ -19,11 +24,45 @@ impl Delay {
     pub fn release(self) -> SYST {
         self.syst
     }
+
+    /// Blocks for the given duration
+    pub fn delay(&mut self, duration: impl Into<Duration>) {
+        // SysTick counts with the core clock
+        let cycles = duration.into().cycles(self.clocks.hclk());
+
+        self.delay_cycles(cycles);
+    }
+
+    fn delay_cycles(&mut self, cycles: u64) {
+        // The reload value register is 24 bits wide
+        const MAX_RVR: u64 = 0x00_FF_FF_FF;
+
+        let mut remaining_cycles = cycles;
+
+        // A reload value of 0 stops the counter, but a single cycle is shorter than the setup anyway
+        while remaining_cycles > 1 {
+            // The counter counts from the reload value down to 0, so a period is RVR + 1 cycles
+            let current_cycles = Ord::min(remaining_cycles, MAX_RVR + 1);
+
+            self.syst.set_reload((current_cycles - 1) as u32);
+            self.syst.clear_current();
+            self.syst.enable_counter();
+
+            // reduce remaining cycles while waiting
+            remaining_cycles -= current_cycles;
+
+            while !self.syst.has_wrapped() {
+                asm::nop();
+            }
+
+            self.syst.disable_counter();
+        }
+    }
 }
 
 impl DelayMs<u32> for Delay {
     fn delay_ms(&mut self, ms: u32) {
-        self.delay_us(ms * 1000)
+        self.delay(Duration::from_millis(ms as u64))
     }
 }
 
@@ -41,36 +80,109 @@ impl DelayMs<u8> for Delay {
 
 impl DelayUs<u32> for Delay {
     fn delay_us(&mut self, us: u32) {
-        const MAX_RVR: u32 = 0x00_FF_FF_FF;
+        self.delay(Duration::from_micros(us as u64))
+    }
+}
 
-        let mut remaining_rvr = us * (self.clocks.sysclk().0 / (HSI / 8));
+impl DelayUs<u16> for Delay {
+    fn delay_us(&mut self, us: u16) {
+        self.delay_us(us as u32)
+    }
+}
 
-        while remaining_rvr != 0 {
-            let current_rvr = Ord::min(remaining_rvr, MAX_RVR);
+impl DelayUs<u8> for Delay {
+    fn delay_us(&mut self, us: u8) {
+        self.delay_us(us as u32)
+    }
+}
 
-            self.syst.set_reload(remaining_rvr);
-            self.syst.clear_current();
-            self.syst.enable_counter();
+/// Delay provider based on one of the timers TIM2 to TIM14
+///
+/// Unlike [`Delay`], it keeps SysTick free, e.g. to be used as tick of an operating system.
+pub struct TimerDelay<TIM> {
+    timer: Timer<TIM>,
+}
 
-            // reduce remaining reset value while waiting
-            remaining_rvr -= current_rvr;
+impl<TIM> TimerDelay<TIM>
+where
+    Timer<TIM>: CountDown<Time = Period>,
+{
+    /// Longest time, for which the timer is started at once.
+    /// It fits into the counter for all timer clocks.
+    const MAX_STEP: Duration = Duration::from_secs(1);
 
-            while !self.syst.has_wrapped() {
-                asm::nop();
-            }
+    pub fn new(timer: Timer<TIM>) -> Self {
+        Self { timer }
+    }
 
-            self.syst.disable_counter();
+    pub fn release(self) -> Timer<TIM> {
+        self.timer
+    }
+
+    /// Blocks for the given duration
+    pub fn delay(&mut self, duration: impl Into<Duration>) {
+        let mut remaining = duration.into();
+
+        while remaining != Duration::ZERO {
+            let current = Ord::min(remaining, Self::MAX_STEP);
+
+            self.timer.start(current);
+            let _ = block!(self.timer.wait());
+
+            remaining -= current;
         }
     }
 }
 
-impl DelayUs<u16> for Delay {
+impl<TIM> DelayMs<u32> for TimerDelay<TIM>
+where
+    Timer<TIM>: CountDown<Time = Period>,
+{
+    fn delay_ms(&mut self, ms: u32) {
+        self.delay(Duration::from_millis(ms as u64))
+    }
+}
+
+impl<TIM> DelayMs<u16> for TimerDelay<TIM>
+where
+    Timer<TIM>: CountDown<Time = Period>,
+{
+    fn delay_ms(&mut self, ms: u16) {
+        self.delay_ms(ms as u32)
+    }
+}
+
+impl<TIM> DelayMs<u8> for TimerDelay<TIM>
+where
+    Timer<TIM>: CountDown<Time = Period>,
+{
+    fn delay_ms(&mut self, ms: u8) {
+        self.delay_ms(ms as u32)
+    }
+}
+
+impl<TIM> DelayUs<u32> for TimerDelay<TIM>
+where
+    Timer<TIM>: CountDown<Time = Period>,
+{
+    fn delay_us(&mut self, us: u32) {
+        self.delay(Duration::from_micros(us as u64))
+    }
+}
+
+impl<TIM> DelayUs<u16> for TimerDelay<TIM>
+where
+    Timer<TIM>: CountDown<Time = Period>,
+{
     fn delay_us(&mut self, us: u16) {
         self.delay_us(us as u32)
     }
 }
 
-impl DelayUs<u8> for Delay {
+impl<TIM> DelayUs<u8> for TimerDelay<TIM>
+where
+    Timer<TIM>: CountDown<Time = Period>,
+{
     fn delay_us(&mut self, us: u8) {
         self.delay_us(us as u32)
     }
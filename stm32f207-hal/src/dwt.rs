@@ -0,0 +1,230 @@
+//! Time measurement with the cycle counter of the Data Watchpoint and Trace unit (DWT)
+//!
+//! The cycle counter is 32 bits wide and counts with the system clock,
+//! so it wraps around after `2^32 / sysclk` seconds (about 35 seconds at 120 MHz).
+
+use cortex_m::peripheral::{DCB, DWT};
+use embedded_hal::blocking::delay::{DelayMs, DelayUs};
+
+use crate::{
+    rcc::Clocks,
+    time::{Duration, Hertz},
+};
+
+/// Extension trait, which enables the cycle counter of the DWT
+pub trait DwtExtension {
+    fn constrain(self, dcb: &mut DCB, clocks: Clocks) -> Dwt;
+}
+
+impl DwtExtension for DWT {
+    fn constrain(mut self, dcb: &mut DCB, clocks: Clocks) -> Dwt {
+        // The DWT only works while tracing is enabled
+        dcb.enable_trace();
+        DWT::unlock();
+        self.enable_cycle_counter();
+
+        Dwt {
+            dwt: self,
+            sysclk: clocks.sysclk(),
+        }
+    }
+}
+
+/// The DWT with an enabled cycle counter
+pub struct Dwt {
+    dwt: DWT,
+    sysclk: Hertz,
+}
+
+impl Dwt {
+    /// Returns the current value of the cycle counter
+    pub fn now(&self) -> Instant {
+        Instant {
+            cycles: DWT::cycle_count(),
+            sysclk: self.sysclk,
+        }
+    }
+
+    /// Creates a stopwatch, which starts immediately
+    pub fn stopwatch(&self) -> Stopwatch {
+        let start = DWT::cycle_count();
+
+        Stopwatch {
+            last: start,
+            elapsed: 0,
+            sysclk: self.sysclk,
+        }
+    }
+
+    /// Creates a delay provider based on the cycle counter
+    ///
+    /// Multiple delays may exist at the same time, as they only read the cycle counter.
+    pub fn delay(&self) -> DwtDelay {
+        DwtDelay {
+            sysclk: self.sysclk,
+        }
+    }
+
+    /// Gives back the DWT
+    ///
+    /// The cycle counter keeps running, because delays, stopwatches and instants
+    /// created from this `Dwt` may still read it.
+    pub fn release(self) -> DWT {
+        self.dwt
+    }
+}
+
+/// Converts cycles of the system clock to a duration
+fn cycles_to_duration(cycles: u64, sysclk: Hertz) -> Duration {
+    Duration::from_micros(cycles * 1_000_000 / sysclk.0 as u64)
+}
+
+/// A point in time, given by the value of the cycle counter
+///
+/// Because the cycle counter wraps around,
+/// an instant can only measure durations shorter than one wrap period.
+/// Use a [`Stopwatch`] for longer durations.
+#[derive(Clone, Copy, Debug)]
+pub struct Instant {
+    cycles: u32,
+    sysclk: Hertz,
+}
+
+impl Instant {
+    /// Returns the value of the cycle counter at this instant
+    pub fn cycles(&self) -> u32 {
+        self.cycles
+    }
+
+    /// Returns the cycles elapsed since this instant
+    pub fn elapsed_cycles(&self) -> u32 {
+        DWT::cycle_count().wrapping_sub(self.cycles)
+    }
+
+    /// Returns the time elapsed since this instant
+    pub fn elapsed(&self) -> Duration {
+        cycles_to_duration(self.elapsed_cycles() as u64, self.sysclk)
+    }
+
+    /// Returns the cycles between the earlier instant and this one
+    pub fn cycles_since(&self, earlier: Instant) -> u32 {
+        self.cycles.wrapping_sub(earlier.cycles)
+    }
+
+    /// Returns the time between the earlier instant and this one
+    pub fn duration_since(&self, earlier: Instant) -> Duration {
+        cycles_to_duration(self.cycles_since(earlier) as u64, self.sysclk)
+    }
+}
+
+/// Measures the time since it has been started
+///
+/// The stopwatch extends the cycle counter to 64 bits,
+/// if it is read at least once per wrap period of the cycle counter.
+pub struct Stopwatch {
+    // value of the cycle counter, when the stopwatch was last read
+    last: u32,
+    elapsed: u64,
+    sysclk: Hertz,
+}
+
+impl Stopwatch {
+    /// Restarts the measurement
+    pub fn reset(&mut self) {
+        self.last = DWT::cycle_count();
+        self.elapsed = 0;
+    }
+
+    /// Returns the cycles since the stopwatch was started
+    pub fn elapsed_cycles(&mut self) -> u64 {
+        let now = DWT::cycle_count();
+
+        self.elapsed += now.wrapping_sub(self.last) as u64;
+        self.last = now;
+
+        self.elapsed
+    }
+
+    /// Returns the time since the stopwatch was started
+    pub fn elapsed(&mut self) -> Duration {
+        cycles_to_duration(self.elapsed_cycles(), self.sysclk)
+    }
+
+    /// Returns the time after which the stopwatch must have been read,
+    /// to not miss a wrap around of the cycle counter
+    pub fn wrap_period(&self) -> Duration {
+        cycles_to_duration(1 << 32, self.sysclk)
+    }
+}
+
+/// Busy waiting delay based on the cycle counter
+///
+/// Unlike the SysTick delay, it allows delays with sub-microsecond resolution.
+#[derive(Clone, Copy)]
+pub struct DwtDelay {
+    sysclk: Hertz,
+}
+
+impl DwtDelay {
+    /// Blocks for the given number of system clock cycles
+    pub fn delay_cycles(&mut self, cycles: u64) {
+        let mut last = DWT::cycle_count();
+        let mut elapsed: u64 = 0;
+
+        // The elapsed cycles are accumulated, so that the delay may be longer than a wrap period
+        while elapsed < cycles {
+            let now = DWT::cycle_count();
+            elapsed += now.wrapping_sub(last) as u64;
+            last = now;
+        }
+    }
+
+    /// Blocks for at least the given number of nanoseconds
+    pub fn delay_ns(&mut self, ns: u32) {
+        // Round up, so that the delay is never too short
+        let cycles = (ns as u64 * self.sysclk.0 as u64).div_ceil(1_000_000_000);
+
+        self.delay_cycles(cycles);
+    }
+
+    /// Blocks for the given duration
+    pub fn delay(&mut self, duration: impl Into<Duration>) {
+        self.delay_cycles(duration.into().cycles(self.sysclk));
+    }
+}
+
+impl DelayUs<u32> for DwtDelay {
+    fn delay_us(&mut self, us: u32) {
+        self.delay_cycles(us as u64 * self.sysclk.0 as u64 / 1_000_000)
+    }
+}
+
+impl DelayUs<u16> for DwtDelay {
+    fn delay_us(&mut self, us: u16) {
+        self.delay_us(us as u32)
+    }
+}
+
+impl DelayUs<u8> for DwtDelay {
+    fn delay_us(&mut self, us: u8) {
+        self.delay_us(us as u32)
+    }
+}
+
+impl DelayMs<u32> for DwtDelay {
+    fn delay_ms(&mut self, ms: u32) {
+        self.delay_cycles(ms as u64 * self.sysclk.0 as u64 / 1_000)
+    }
+}
+
+impl DelayMs<u16> for DwtDelay {
+    fn delay_ms(&mut self, ms: u16) {
+        self.delay_ms(ms as u32)
+    }
+}
+
+impl DelayMs<u8> for DwtDelay {
+    fn delay_ms(&mut self, ms: u8) {
+        self.delay_ms(ms as u32)
+    }
+}
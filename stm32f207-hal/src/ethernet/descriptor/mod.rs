@@ -7,8 +7,13 @@ mod buffer_lengths;
 use buffer_lengths::*;
 use vcell::VolatileCell;
 
-use super::ring::{Receive, Transmit};
+use super::{
+    errors::{ReceiveFrameErrors, TransmitFrameErrors},
+    ptp::Timestamp,
+    ring::{Receive, Transmit},
+};
 
+/// A descriptor in the enhanced (alternate) layout, which is required for timestamps
 #[repr(C)]
 pub struct Descriptor<T> {
     status: VolatileCell<Status<T>>,
@@ -17,6 +22,12 @@ pub struct Descriptor<T> {
     // Could also be a pointer to a second buffer,
     // but we will only use the chained mode where it points to the next descriptor.
     next_descriptor: VolatileCell<*const Descriptor<T>>,
+    // Written by the DMA for received frames, with the results of the checksum offload
+    extended_status: VolatileCell<u32>,
+    _reserved: VolatileCell<u32>,
+    // Snapshot of the PTP clock, if the frame has been timestamped
+    timestamp_low: VolatileCell<u32>,
+    timestamp_high: VolatileCell<u32>,
 }
 
 impl Descriptor<Transmit> {
@@ -26,9 +37,46 @@ impl Descriptor<Transmit> {
             buffer_lengths: VolatileCell::new(BufferLengths::new_transmit()),
             buffer1: VolatileCell::new(null_mut()),
             next_descriptor: VolatileCell::new(null()),
+            extended_status: VolatileCell::new(0),
+            _reserved: VolatileCell::new(0),
+            timestamp_low: VolatileCell::new(0),
+            timestamp_high: VolatileCell::new(0),
         }
     }
 
+    /// Returns the time, at which the frame has been transmitted, if it has been timestamped
+    pub(crate) unsafe fn timestamp(&self) -> Option<Timestamp> {
+        if self.status.get().has_timestamp() {
+            Some(self.read_timestamp())
+        } else {
+            None
+        }
+    }
+
+    /// Returns the errors of the last frame, which has been transmitted from this descriptor,
+    /// and clears them, so that they are only reported once
+    pub(crate) unsafe fn take_errors(&mut self) -> Option<TransmitFrameErrors> {
+        let mut status = self.status.get();
+
+        if !status.has_error() {
+            return None;
+        }
+
+        let errors = status.errors();
+        status.clear_errors();
+        self.status.set(status);
+
+        Some(errors)
+    }
+
+    pub(crate) unsafe fn set_segments(&mut self, first: bool, last: bool) {
+        let mut status = self.status.get();
+
+        status.set_segments(first, last);
+
+        self.status.set(status)
+    }
+
     pub(crate) unsafe fn set_transmit_end_of_ring(&mut self) {
         let mut status = self.status.get();
 
@@ -45,16 +93,72 @@ impl Descriptor<Receive> {
             buffer_lengths: VolatileCell::new(BufferLengths::new_receive()),
             buffer1: VolatileCell::new(null_mut()),
             next_descriptor: VolatileCell::new(null()),
+            extended_status: VolatileCell::new(0),
+            _reserved: VolatileCell::new(0),
+            timestamp_low: VolatileCell::new(0),
+            timestamp_high: VolatileCell::new(0),
         }
     }
 
-    /// Returns the length of the frame in buffer1.
+    /// Returns the length of the whole frame.
     ///
-    /// If the frame did not fit in buffer1 and was trundcated, none is returned
+    /// Only the descriptor of the last segment holds the length, errors and timestamp of the frame,
+    /// for the other descriptors, none is returned
     pub(crate) unsafe fn get_frame_len(&self) -> Option<usize> {
         self.status.get().get_frame_len()
     }
 
+    // Bits of the extended status
+    const IP_HEADER_ERROR_MASK: u32 = 1 << 3;
+    const IP_PAYLOAD_ERROR_MASK: u32 = 1 << 4;
+
+    /// Returns true, if the descriptor holds the first part of a frame
+    pub(crate) unsafe fn is_first_segment(&self) -> bool {
+        self.status.get().is_start_of_frame()
+    }
+
+    /// Returns true, if the tag of the frame matches the VLAN tag register
+    ///
+    /// Only the descriptor of the last segment holds this status.
+    pub(crate) unsafe fn matches_vlan_tag(&self) -> bool {
+        self.status.get().is_vlan_frame()
+    }
+
+    /// Returns the errors of the frame, including a wrong IP header or payload checksum,
+    /// or None, if the frame is valid
+    pub(crate) unsafe fn errors(&self) -> Option<ReceiveFrameErrors> {
+        let status = self.status.get();
+        let extended_status = if status.has_extended_status() {
+            self.extended_status.get()
+        } else {
+            0
+        };
+
+        let ip_header_checksum = extended_status & Self::IP_HEADER_ERROR_MASK != 0;
+        let ip_payload_checksum = extended_status & Self::IP_PAYLOAD_ERROR_MASK != 0;
+
+        if !status.has_error() && !ip_header_checksum && !ip_payload_checksum {
+            return None;
+        }
+
+        Some(ReceiveFrameErrors {
+            ip_header_checksum,
+            ip_payload_checksum,
+            ..status.errors()
+        })
+    }
+
+    /// Returns the time, at which the frame has been received, if it has been timestamped
+    ///
+    /// Only valid, if timestamping is enabled.
+    pub(crate) unsafe fn timestamp(&self) -> Option<Timestamp> {
+        if self.status.get().has_timestamp() {
+            Some(self.read_timestamp())
+        } else {
+            None
+        }
+    }
+
     pub(crate) unsafe fn set_receive_end_of_ring(&mut self) {
         let mut buffer_lengths = self.buffer_lengths.get();
 
@@ -83,10 +187,6 @@ impl<T> Descriptor<T> {
         self.status.set(status);
     }
 
-    pub(crate) unsafe fn has_error(&self) -> bool {
-        self.status.get().has_error()
-    }
-
     pub(crate) unsafe fn set_buffer1(
         &mut self,
         buf: *mut u8,
@@ -108,6 +208,10 @@ impl<T> Descriptor<T> {
         Ok(())
     }
 
+    unsafe fn read_timestamp(&self) -> Timestamp {
+        Timestamp::from_registers(self.timestamp_high.get(), self.timestamp_low.get())
+    }
+
     pub(crate) unsafe fn set_next_descriptor(&mut self, next: *const Descriptor<T>) {
         self.next_descriptor.set(next);
     }
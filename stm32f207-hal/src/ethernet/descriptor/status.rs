@@ -1,6 +1,7 @@
 use core::marker::PhantomData;
 
 use super::{Receive, Transmit};
+use crate::ethernet::errors::{ReceiveFrameErrors, TransmitFrameErrors};
 
 #[repr(transparent)]
 pub(crate) struct Status<T>(u32, PhantomData<T>);
@@ -38,21 +39,92 @@ impl Status<Transmit> {
     const LAST_SEGMENT_MASK: u32 = 1 << 29;
     const FIRST_SEGMENT_MASK: u32 = 1 << 28;
     const SECOND_ADDRESS_CHAINED_MASK: u32 = 1 << 20;
+    // Take a snapshot of the PTP clock, when the frame is transmitted
+    const TRANSMIT_TIMESTAMP_ENABLE_MASK: u32 = 1 << 25;
+    // Insert the IPv4 header checksum, and the TCP, UDP or ICMP checksum including the pseudo-header
+    const CHECKSUM_INSERTION_FULL_MASK: u32 = 0b11 << 22;
 
     pub(crate) const fn new_transmit() -> Self {
         const DEFAULT_STATUS: u32 = Status::<Transmit>::INTERRUPT_COMPLETION_MASK
             | Status::<Transmit>::LAST_SEGMENT_MASK
             | Status::<Transmit>::FIRST_SEGMENT_MASK
-            | Status::<Transmit>::SECOND_ADDRESS_CHAINED_MASK;
+            | Status::<Transmit>::SECOND_ADDRESS_CHAINED_MASK
+            | Status::<Transmit>::CHECKSUM_INSERTION_FULL_MASK
+            | Status::<Transmit>::TRANSMIT_TIMESTAMP_ENABLE_MASK;
 
         Self(DEFAULT_STATUS, PhantomData)
     }
 
+    /// Marks the descriptor as the first and/or last segment of a frame
+    ///
+    /// Only the last segment triggers the interrupt on completion.
+    pub(crate) fn set_segments(&mut self, first: bool, last: bool) {
+        self.0 &=
+            !(Self::FIRST_SEGMENT_MASK | Self::LAST_SEGMENT_MASK | Self::INTERRUPT_COMPLETION_MASK);
+
+        if first {
+            self.0 |= Self::FIRST_SEGMENT_MASK;
+        }
+
+        if last {
+            self.0 |= Self::LAST_SEGMENT_MASK | Self::INTERRUPT_COMPLETION_MASK;
+        }
+    }
+
     const TRANSMIT_END_OF_RING_MASK: u32 = 1 << 21;
 
     pub(crate) fn set_transmit_end_of_ring(&mut self) {
         self.0 |= Self::TRANSMIT_END_OF_RING_MASK
     }
+
+    const TRANSMIT_TIMESTAMP_STATUS_MASK: u32 = 1 << 17;
+
+    /// Returns true, if the frame has been timestamped
+    pub(crate) fn has_timestamp(&self) -> bool {
+        self.0 & Self::TRANSMIT_TIMESTAMP_STATUS_MASK != 0
+    }
+
+    const IP_HEADER_ERROR_MASK: u32 = 1 << 16;
+    const JABBER_TIMEOUT_MASK: u32 = 1 << 14;
+    const FRAME_FLUSHED_MASK: u32 = 1 << 13;
+    const IP_PAYLOAD_ERROR_MASK: u32 = 1 << 12;
+    const LOSS_OF_CARRIER_MASK: u32 = 1 << 11;
+    const NO_CARRIER_MASK: u32 = 1 << 10;
+    const LATE_COLLISION_MASK: u32 = 1 << 9;
+    const EXCESSIVE_COLLISION_MASK: u32 = 1 << 8;
+    const EXCESSIVE_DEFERRAL_MASK: u32 = 1 << 2;
+    const UNDERFLOW_ERROR_MASK: u32 = 1 << 1;
+
+    const ERROR_MASKS: u32 = Self::ERROR_SUMMARY_MASK
+        | Self::IP_HEADER_ERROR_MASK
+        | Self::JABBER_TIMEOUT_MASK
+        | Self::FRAME_FLUSHED_MASK
+        | Self::IP_PAYLOAD_ERROR_MASK
+        | Self::LOSS_OF_CARRIER_MASK
+        | Self::NO_CARRIER_MASK
+        | Self::LATE_COLLISION_MASK
+        | Self::EXCESSIVE_COLLISION_MASK
+        | Self::EXCESSIVE_DEFERRAL_MASK
+        | Self::UNDERFLOW_ERROR_MASK;
+
+    pub(crate) fn errors(&self) -> TransmitFrameErrors {
+        TransmitFrameErrors {
+            jabber_timeout: self.0 & Self::JABBER_TIMEOUT_MASK != 0,
+            frame_flushed: self.0 & Self::FRAME_FLUSHED_MASK != 0,
+            ip_payload_checksum: self.0 & Self::IP_PAYLOAD_ERROR_MASK != 0,
+            loss_of_carrier: self.0 & Self::LOSS_OF_CARRIER_MASK != 0,
+            no_carrier: self.0 & Self::NO_CARRIER_MASK != 0,
+            late_collision: self.0 & Self::LATE_COLLISION_MASK != 0,
+            excessive_collisions: self.0 & Self::EXCESSIVE_COLLISION_MASK != 0,
+            excessive_deferral: self.0 & Self::EXCESSIVE_DEFERRAL_MASK != 0,
+            underflow: self.0 & Self::UNDERFLOW_ERROR_MASK != 0,
+            ip_header_checksum: self.0 & Self::IP_HEADER_ERROR_MASK != 0,
+        }
+    }
+
+    pub(crate) fn clear_errors(&mut self) {
+        self.0 &= !Self::ERROR_MASKS
+    }
 }
 
 impl Status<Receive> {
@@ -68,8 +140,9 @@ impl Status<Receive> {
     const FRAME_LENGTH_SHIFT: u32 = 16;
     const FRAME_LENGTH_MASK: u32 = 0x1FFF << Status::<Receive>::FRAME_LENGTH_SHIFT;
 
+    /// Returns the length of the whole frame, which is only held by its last descriptor
     pub(crate) unsafe fn get_frame_len(&self) -> Option<usize> {
-        if self.is_start_of_frame() && self.is_end_of_frame() {
+        if self.is_end_of_frame() {
             let length = (self.0 & Status::<Receive>::FRAME_LENGTH_MASK)
                 >> Status::<Receive>::FRAME_LENGTH_SHIFT;
 
@@ -79,7 +152,53 @@ impl Status<Receive> {
         None
     }
 
-    fn is_start_of_frame(&self) -> bool {
+    // With the enhanced descriptor layout, these bits refer to the additional words
+    const EXTENDED_STATUS_AVAILABLE_MASK: u32 = 1 << 0;
+    const TIMESTAMP_VALID_MASK: u32 = 1 << 7;
+
+    /// Returns true, if the extended status holds the results of the checksum offload
+    pub(crate) fn has_extended_status(&self) -> bool {
+        self.0 & Self::EXTENDED_STATUS_AVAILABLE_MASK != 0
+    }
+
+    /// Returns true, if the frame has been timestamped
+    pub(crate) fn has_timestamp(&self) -> bool {
+        self.0 & Self::TIMESTAMP_VALID_MASK != 0
+    }
+
+    const VLAN_TAG_MASK: u32 = 1 << 10;
+
+    /// Returns true, if the frame is tagged, and its tag matches the VLAN tag register
+    pub(crate) fn is_vlan_frame(&self) -> bool {
+        self.0 & Self::VLAN_TAG_MASK != 0
+    }
+
+    const DESCRIPTOR_ERROR_MASK: u32 = 1 << 14;
+    const LENGTH_ERROR_MASK: u32 = 1 << 12;
+    const OVERFLOW_ERROR_MASK: u32 = 1 << 11;
+    const LATE_COLLISION_MASK: u32 = 1 << 6;
+    const WATCHDOG_TIMEOUT_MASK: u32 = 1 << 4;
+    const RECEIVE_ERROR_MASK: u32 = 1 << 3;
+    const DRIBBLE_BIT_ERROR_MASK: u32 = 1 << 2;
+    const CRC_ERROR_MASK: u32 = 1 << 1;
+
+    /// Decodes the error bits, the checksum errors are held by the extended status
+    pub(crate) fn errors(&self) -> ReceiveFrameErrors {
+        ReceiveFrameErrors {
+            crc: self.0 & Self::CRC_ERROR_MASK != 0,
+            dribble_bit: self.0 & Self::DRIBBLE_BIT_ERROR_MASK != 0,
+            receive: self.0 & Self::RECEIVE_ERROR_MASK != 0,
+            watchdog_timeout: self.0 & Self::WATCHDOG_TIMEOUT_MASK != 0,
+            late_collision: self.0 & Self::LATE_COLLISION_MASK != 0,
+            overflow: self.0 & Self::OVERFLOW_ERROR_MASK != 0,
+            length: self.0 & Self::LENGTH_ERROR_MASK != 0,
+            descriptor: self.0 & Self::DESCRIPTOR_ERROR_MASK != 0,
+            ip_header_checksum: false,
+            ip_payload_checksum: false,
+        }
+    }
+
+    pub(crate) fn is_start_of_frame(&self) -> bool {
         self.0 & Status::<Receive>::FIRST_SEGMENT_MASK != 0
     }
 
@@ -1,17 +1,24 @@
-use crate::rcc::Clocks;
-use cortex_m::interrupt;
+use crate::{rcc::Clocks, time::Hertz};
+use cortex_m::{interrupt, peripheral::NVIC};
+#[cfg(feature = "smoltcp")]
 use smoltcp::{
-    phy::{Device, DeviceCapabilities, RxToken, TxToken},
+    phy::{ChecksumCapabilities, Device, DeviceCapabilities, RxToken, TxToken},
     Error,
 };
-use stm32f2::stm32f217::{ETHERNET_DMA, ETHERNET_MAC, ETHERNET_PTP, RCC, SYSCFG};
+use stm32f2::stm32f217::{Interrupt, ETHERNET_DMA, ETHERNET_MAC, ETHERNET_PTP, RCC, SYSCFG};
 
 use super::{
-    pins::EthernetPins,
-    receive::{ReceiveFrame, ReceiveRing},
-    ring::{Receive, RingEntry, Transmit},
-    transmit::{TransmitError, TransmitRing},
-    MAX_TRANSMISSION_UNIT,
+    errors::ErrorCounters,
+    filter::{self, FrameFilter, VlanFilter},
+    mdio::Mdio,
+    phy::{Duplex, Link, Phy, PhyError, Speed},
+    pins::{EthernetPins, MdioPins},
+    ptp::{self, PtpClock, PtpError, Timestamp},
+    receive::{ReceiveError, ReceiveFrame, ReceiveRing},
+    ring::{Receive, Ring, Transmit, DEFAULT_BUFFER_SIZE},
+    transmit::{TransmitError, TransmitHandle, TransmitRing},
+    vlan::{self, VlanTag},
+    FCS_LENGTH, FIFO_SIZE, MAX_TRANSMISSION_UNIT, VLAN_TAG_LENGTH,
 };
 
 #[derive(Debug, PartialEq, Eq)]
@@ -19,40 +26,68 @@ pub enum EthernetDeviceError {
     WrongClocks,
 }
 
-pub struct EthernetDevice<'r, 't> {
+/// A change of the link, reported by `EthernetDevice::poll_link`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LinkEvent {
+    /// The link has been established, and the MAC has been configured for it
+    Up(Link),
+    Down,
+}
+
+/// The Ethernet MAC and its DMA
+///
+/// `N` is the size of the buffers of the ring entries, see [`RingEntry`](super::ring::RingEntry).
+pub struct EthernetDevice<'r, 't, const N: usize = DEFAULT_BUFFER_SIZE> {
     // We take ownership of ETHERNET_MAC so that no one else can change the config
-    #[allow(dead_code)]
     ethernet_mac: ETHERNET_MAC,
     ethernet_dma: ETHERNET_DMA,
-    // we don't use it directly, but the extended descriptor format could otherwise be enabled
-    #[allow(dead_code)]
+    // The PTP clock is only accessed through PtpClock, which borrows the device
     ethernet_ptp: ETHERNET_PTP,
-    // we can allow access from ETHERNET_MMC however, because it only reads statistics about the device
-    receive_ring: ReceiveRing<'r>,
-    transmit_ring: TransmitRing<'t>,
+    // we can allow access from ETHERNET_MMC however, because it only reads statistics about the device,
+    // see `ethernet::statistics::StatisticsCounters`
+    receive_ring: ReceiveRing<'r, N>,
+    transmit_ring: TransmitRing<'t, N>,
+    link: Option<Link>,
+    jumbo_frames: bool,
+    error_counters: ErrorCounters,
+    mac_address: [u8; 6],
+    hclk: Hertz,
+    // Addend for the nominal frequency of the PTP clock, once timestamping is enabled
+    ptp_addend: Option<u32>,
 }
 
-impl<'r, 't> EthernetDevice<'r, 't> {
+impl<'r, 't, const N: usize> EthernetDevice<'r, 't, N> {
     /// Creates and Initializes the EthernetDevice
     ///
     /// In addition, make sure the following jumpers are set one the board:
     /// JP5 connects pin 1 and 2 to provide the clock by the external crystal
-    /// JP6 connects pin 2 and 3 to select MII
+    /// JP6 connects pin 2 and 3 to select MII, or pin 1 and 2 to select RMII
     /// JP8 is fitted so that the EthernetDevice is enabled
     /// Refer to the board manual for more details on how to set up Ethernet.
+    ///
+    /// The MAC address is used to filter the received unicast frames.
+    /// Broadcast frames are received, and multicast frames are filtered by the empty hash table,
+    /// see [`FrameFilter::default`].
+    // NOTE(allow) The peripherals, buffers and pins are all required, and can not be grouped sensibly
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         mut ethernet_mac: ETHERNET_MAC,
         mut ethernet_dma: ETHERNET_DMA,
         ethernet_ptp: ETHERNET_PTP,
-        receive_buffer: &'r mut [RingEntry<Receive>],
-        transmit_buffer: &'t mut [RingEntry<Transmit>],
+        receive_buffer: Ring<'r, Receive, N>,
+        transmit_buffer: Ring<'t, Transmit, N>,
+        mac_address: [u8; 6],
         clocks: Clocks,
         pins: impl EthernetPins,
     ) -> Result<Self, EthernetDeviceError> {
-        Self::setup_rcc();
+        Self::setup_rcc(pins.is_rmii());
         Self::reset_dma(&mut ethernet_dma);
         Self::setup(&mut ethernet_mac, &mut ethernet_dma, clocks)?;
 
+        filter::set_mac_address(&ethernet_mac, mac_address);
+        filter::clear_hash_table(&ethernet_mac);
+        FrameFilter::default().apply(&ethernet_mac);
+
         // The Ethernetpins are not used directly,
         // they are just required to be configured this way
         // in order to connect to the physical device
@@ -70,11 +105,335 @@ impl<'r, 't> EthernetDevice<'r, 't> {
             ethernet_ptp,
             receive_ring,
             transmit_ring,
+            link: None,
+            jumbo_frames: false,
+            error_counters: ErrorCounters::default(),
+            mac_address,
+            hclk: clocks.hclk(),
+            ptp_addend: None,
         };
 
         Ok(result)
     }
 
+    /// Gives access to the registers of the PHY
+    ///
+    /// The MDIO pins are only required to be configured this way.
+    pub fn mdio(&mut self, pins: &MdioPins) -> Mdio<'_> {
+        let _ = pins;
+
+        Mdio::new(&self.ethernet_mac)
+    }
+
+    pub fn mac_address(&self) -> [u8; 6] {
+        self.mac_address
+    }
+
+    /// Selects the frames, which are received
+    pub fn set_frame_filter(&mut self, frame_filter: FrameFilter) {
+        frame_filter.apply(&self.ethernet_mac);
+    }
+
+    /// Adds the multicast address to the hash table
+    pub fn add_multicast_address(&mut self, address: [u8; 6]) {
+        filter::add_to_hash_table(&self.ethernet_mac, address);
+    }
+
+    /// Removes all multicast addresses from the hash table
+    pub fn clear_multicast_addresses(&mut self) {
+        filter::clear_hash_table(&self.ethernet_mac);
+    }
+
+    /// Selects the VLAN frames, which are received, None receives all of them
+    pub fn set_vlan_filter(&mut self, vlan_filter: Option<VlanFilter>) {
+        filter::set_vlan_filter(&self.ethernet_mac, vlan_filter);
+        self.receive_ring.set_vlan_filtering(vlan_filter.is_some());
+    }
+
+    /// Allows frames, which are longer than `MAX_TRANSMISSION_UNIT`, to be received and transmitted
+    ///
+    /// Disables the receive watchdog and the transmit jabber timer of the MAC.
+    /// The DMA runs in store-and-forward mode, and the MAC inserts the checksums,
+    /// which both require the whole frame in the FIFO, so frames are limited to its 2048 bytes.
+    /// Frames spanning several entries are limited to `MAX_TRANSMISSION_UNIT`,
+    /// so jumbo frames are only received and transmitted, if they fit into a single entry of `N` bytes.
+    pub fn set_jumbo_frames(&mut self, enabled: bool) {
+        self.ethernet_mac
+            .maccr
+            .modify(|_, w| w.wd().bit(enabled).jd().bit(enabled));
+
+        self.jumbo_frames = enabled;
+    }
+
+    /// Returns the length of the longest frame, which can be received and transmitted,
+    /// including a VLAN tag and the FCS
+    ///
+    /// Frames spanning several entries are copied, so they are limited to `MAX_TRANSMISSION_UNIT`,
+    /// and jumbo frames are limited by the FIFOs of the MAC.
+    pub fn max_frame_length(&self) -> usize {
+        if self.jumbo_frames {
+            N.clamp(MAX_TRANSMISSION_UNIT, FIFO_SIZE)
+        } else {
+            MAX_TRANSMISSION_UNIT
+        }
+    }
+
+    /// Returns the length of the longest untagged frame, including the Ethernet header,
+    /// but not the FCS, as expected by smoltcp
+    pub fn max_transmission_unit(&self) -> usize {
+        self.max_frame_length() - VLAN_TAG_LENGTH - FCS_LENGTH
+    }
+
+    /// Enables the interrupts for received and transmitted frames
+    ///
+    /// The `ETH` interrupt must call `ethernet::interrupt::on_interrupt`.
+    pub fn listen(&mut self) {
+        self.ethernet_dma
+            .dmaier
+            .modify(|_, w| w.nise().set_bit().rie().set_bit().tie().set_bit());
+
+        // Safety: The interrupt handler only acknowledges the status flags
+        unsafe { NVIC::unmask(Interrupt::ETH) };
+    }
+
+    pub fn unlisten(&mut self) {
+        NVIC::mask(Interrupt::ETH);
+
+        self.ethernet_dma
+            .dmaier
+            .modify(|_, w| w.nise().clear_bit().rie().clear_bit().tie().clear_bit());
+    }
+
+    /// Enables the timestamping of all received and transmitted frames, and starts the PTP clock
+    ///
+    /// The clock starts at zero, and runs with its nominal frequency derived from HCLK.
+    pub fn enable_timestamping(&mut self) -> Result<(), PtpError> {
+        if self.ptp_addend.is_none() {
+            let addend = ptp::enable(&self.ethernet_mac, &self.ethernet_ptp, self.hclk)?;
+            self.ptp_addend = Some(addend);
+        }
+
+        Ok(())
+    }
+
+    /// Gives access to the PTP clock
+    ///
+    /// Returns None, if timestamping has not been enabled.
+    pub fn ptp_clock(&mut self) -> Option<PtpClock<'_>> {
+        let addend = self.ptp_addend?;
+
+        Some(PtpClock::new(
+            &self.ethernet_mac,
+            &self.ethernet_ptp,
+            addend,
+        ))
+    }
+
+    /// Returns the time, at which the last frame has been received
+    pub fn last_receive_timestamp(&self) -> Option<Timestamp> {
+        self.ptp_addend?;
+
+        self.receive_ring.last_timestamp()
+    }
+
+    /// Returns the time, at which the last frame has been transmitted
+    ///
+    /// Returns None, while the frame has not been sent yet.
+    pub fn last_transmit_timestamp(&self) -> Option<Timestamp> {
+        self.ptp_addend?;
+
+        self.transmit_ring.last_timestamp()
+    }
+
+    /// Returns the time, at which the frame of the handle has been transmitted
+    ///
+    /// Returns None, while the frame has not been sent yet,
+    /// or once its entry has been reused for another frame.
+    pub fn transmit_timestamp(&self, handle: TransmitHandle) -> Option<Timestamp> {
+        self.ptp_addend?;
+
+        self.transmit_ring.timestamp(handle)
+    }
+
+    /// Returns the next received frame, or None, if no frame has been received
+    ///
+    /// Frames, which can not be received, are skipped and counted in the error counters.
+    pub fn receive(&mut self) -> Option<Frame<'_>> {
+        self.recover();
+
+        let receive_frame = receive_frame(&mut self.receive_ring, &mut self.error_counters)?;
+
+        Some(Frame {
+            receive_frame,
+            timestamping: self.ptp_addend.is_some(),
+        })
+    }
+
+    /// Transmits the frame, which has to include the Ethernet header, but not the FCS
+    ///
+    /// The handle gives access to the timestamp of the frame, once it has been sent.
+    pub fn send(&mut self, frame: &[u8]) -> Result<TransmitHandle, TransmitError> {
+        let ((), handle) =
+            self.transmit_with(frame.len(), |buffer| buffer.copy_from_slice(frame))?;
+
+        Ok(handle)
+    }
+
+    /// Transmits the untagged frame with the VLAN tag inserted after the source address
+    pub fn send_tagged(
+        &mut self,
+        frame: &[u8],
+        tag: VlanTag,
+    ) -> Result<TransmitHandle, TransmitError> {
+        if frame.len() < vlan::HEADER_LENGTH {
+            return Err(TransmitError::FrameTooShort);
+        }
+
+        let ((), handle) = self.transmit_with(frame.len() + VLAN_TAG_LENGTH, |buffer| {
+            vlan::insert_tag(frame, tag, buffer)
+        })?;
+
+        Ok(handle)
+    }
+
+    /// Transmits a frame of the given length, which is written into the buffer by the closure
+    ///
+    /// This avoids copying the frame, if it fits into a single entry.
+    /// Returns the result of the closure, and the handle of the frame.
+    pub fn transmit_with<R>(
+        &mut self,
+        length: usize,
+        f: impl FnOnce(&mut [u8]) -> R,
+    ) -> Result<(R, TransmitHandle), TransmitError> {
+        self.transmitter().transmit(length, f)
+    }
+
+    /// Returns the next received frame, and the transmit ring, e.g. to reply to the frame
+    #[cfg(any(feature = "smoltcp", feature = "embassy-net"))]
+    pub(crate) fn receive_with_transmitter(
+        &mut self,
+    ) -> (Option<ReceiveFrame<'_>>, Transmitter<'_, 't, N>) {
+        self.recover();
+
+        let Self {
+            ref mut receive_ring,
+            ref mut transmit_ring,
+            ref ethernet_dma,
+            ref mut error_counters,
+            ..
+        } = self;
+
+        let receive_frame = receive_frame(receive_ring, error_counters);
+
+        (
+            receive_frame,
+            Transmitter {
+                transmit_ring,
+                ethernet_dma,
+                error_counters,
+            },
+        )
+    }
+
+    pub(crate) fn transmitter(&mut self) -> Transmitter<'_, 't, N> {
+        self.recover();
+
+        Transmitter {
+            transmit_ring: &mut self.transmit_ring,
+            ethernet_dma: &self.ethernet_dma,
+            error_counters: &mut self.error_counters,
+        }
+    }
+
+    /// Returns the counters of lost and erroneous frames
+    pub fn error_counters(&self) -> ErrorCounters {
+        self.error_counters
+    }
+
+    pub fn reset_error_counters(&mut self) {
+        self.error_counters = ErrorCounters::default();
+    }
+
+    /// Resumes the DMA, if it has been suspended, and counts the frames, which it dropped
+    ///
+    /// The reception is suspended, when no entry is available, and the transmission,
+    /// when no frame is ready or after an underflow. Both only resume on a poll demand.
+    fn recover(&mut self) {
+        let status = self.ethernet_dma.dmasr.read();
+
+        if status.rbus().bit_is_set() {
+            self.ethernet_dma
+                .dmasr
+                .write(|w| w.rbus().set_bit().ais().set_bit());
+            self.error_counters.count_receive_buffer_unavailable();
+            self.receive_ring.request_poll(&self.ethernet_dma);
+        }
+
+        if status.tus().bit_is_set() {
+            self.ethernet_dma
+                .dmasr
+                .write(|w| w.tus().set_bit().ais().set_bit());
+            self.error_counters.count_transmit_underflow();
+            self.transmit_ring.request_poll(&self.ethernet_dma);
+        }
+
+        if status.tbus().bit_is_set() {
+            self.ethernet_dma.dmasr.write(|w| w.tbus().set_bit());
+            self.transmit_ring.request_poll(&self.ethernet_dma);
+        }
+
+        if status.ros().bit_is_set() {
+            self.ethernet_dma
+                .dmasr
+                .write(|w| w.ros().set_bit().ais().set_bit());
+        }
+
+        // Reading the counters clears them
+        let missed = self.ethernet_dma.dmamfbocr.read();
+        let dropped_frames = missed.mfc().bits() as u32 + missed.mfa().bits() as u32;
+        self.error_counters.count_dropped_frames(dropped_frames);
+    }
+
+    /// Returns the link as of the last call of `poll_link`, or None, if the link is down
+    pub fn link(&self) -> Option<Link> {
+        self.link
+    }
+
+    /// Reads the link from the PHY, and reports, if it went up or down since the last call
+    ///
+    /// When the link comes up, the MAC is configured for its speed and duplex mode.
+    /// Should be called periodically, e.g. once per second.
+    pub fn poll_link(
+        &mut self,
+        phy: &mut impl Phy,
+        pins: &MdioPins,
+    ) -> Result<Option<LinkEvent>, PhyError> {
+        let link = phy.link(&mut self.mdio(pins))?;
+
+        if link == self.link {
+            return Ok(None);
+        }
+
+        self.link = link;
+
+        match link {
+            Some(link) => {
+                self.set_link(link);
+                Ok(Some(LinkEvent::Up(link)))
+            }
+            None => Ok(Some(LinkEvent::Down)),
+        }
+    }
+
+    fn set_link(&mut self, link: Link) {
+        self.ethernet_mac.maccr.modify(|_, w| {
+            w.fes()
+                .bit(link.speed == Speed::Mbps100)
+                .dm()
+                .bit(link.duplex == Duplex::Full)
+        });
+    }
+
     fn setup(
         ethernet_mac: &mut ETHERNET_MAC,
         ethernet_dma: &mut ETHERNET_DMA,
@@ -108,17 +467,14 @@ impl<'r, 't> EthernetDevice<'r, 't> {
                 .set_bit()
                 .apcs()
                 .set_bit()
+                .ipco()
+                .set_bit()
                 .re()
                 .set_bit()
                 .te()
                 .set_bit()
         });
 
-        // frame filter
-        ethernet_mac
-            .macffr
-            .modify(|_, w| w.ra().set_bit().pm().set_bit());
-
         const ETHERNET_MACFCR_PAUSE_TIME: u16 = 0x100;
 
         // flow control
@@ -127,8 +483,11 @@ impl<'r, 't> EthernetDevice<'r, 't> {
             .modify(|_, w| unsafe { w.pt().bits(ETHERNET_MACFCR_PAUSE_TIME) });
 
         // bus mode
+        // The enhanced descriptor layout holds the timestamps
         ethernet_dma.dmabmr.modify(|_, w| unsafe {
-            w.aab()
+            w.edfe()
+                .set_bit()
+                .aab()
                 .set_bit()
                 .usp()
                 .set_bit()
@@ -170,7 +529,7 @@ impl<'r, 't> EthernetDevice<'r, 't> {
         }
     }
 
-    fn setup_rcc() {
+    fn setup_rcc(rmii: bool) {
         // will only be used to change the Ethernet
         interrupt::free(|_| {
             let rcc;
@@ -189,8 +548,12 @@ impl<'r, 't> EthernetDevice<'r, 't> {
                 rcc.ahb1enr.modify(|_, w| w.ethmacen().clear_bit());
             }
 
-            // Enable MII
-            syscfg.pmc.modify(|_, w| w.mii_rmii_sel().clear_bit());
+            // The interface must be selected while the MAC is in reset,
+            // and before its clocks are enabled
+            rcc.ahb1rstr.modify(|_, w| w.ethmacrst().set_bit());
+
+            // Select MII or RMII
+            syscfg.pmc.modify(|_, w| w.mii_rmii_sel().bit(rmii));
 
             // Set ethernet clocks
             rcc.ahb1enr.modify(|_, w| {
@@ -200,73 +563,157 @@ impl<'r, 't> EthernetDevice<'r, 't> {
                     .set_bit()
                     .ethmacrxen()
                     .set_bit()
+                    .ethmacptpen()
+                    .set_bit()
             });
 
-            // Reset MAC
-            rcc.ahb1rstr.modify(|_, w| w.ethmacrst().set_bit());
+            // Release the reset of the MAC
             rcc.ahb1rstr.modify(|_, w| w.ethmacrst().clear_bit());
         });
     }
 }
 
-impl<'a, 'r, 't> Device<'a> for EthernetDevice<'r, 't>
+/// Counts and skips the frames, which can not be received, and returns the next valid one
+fn receive_frame<'a, const N: usize>(
+    receive_ring: &'a mut ReceiveRing<'_, N>,
+    error_counters: &mut ErrorCounters,
+) -> Option<ReceiveFrame<'a>> {
+    loop {
+        match receive_ring.poll_frame() {
+            Ok(_) => break,
+            Err(ReceiveError::BufferEmpty) => return None,
+            Err(ReceiveError::FrameTruncated) => error_counters.count_truncated_frame(),
+            Err(ReceiveError::DMAError(errors)) => error_counters.count_receive_errors(errors),
+            // Filtered frames are not errors
+            Err(ReceiveError::VlanMismatch) => {}
+        }
+    }
+
+    receive_ring.receive_frame().ok()
+}
+
+/// The transmit ring, borrowed from the device, to transmit a single frame
+pub(crate) struct Transmitter<'a, 'b, const N: usize> {
+    transmit_ring: &'a mut TransmitRing<'b, N>,
+    ethernet_dma: &'a ETHERNET_DMA,
+    error_counters: &'a mut ErrorCounters,
+}
+
+impl<'a, 'b, const N: usize> Transmitter<'a, 'b, N> {
+    /// Returns true, if a frame of the length fits into the transmit ring
+    #[cfg(feature = "embassy-net")]
+    pub(crate) fn fits(&self, length: usize) -> bool {
+        self.transmit_ring.fits(length)
+    }
+
+    /// Transmits the frame, or drops and counts it, if it can not be transmitted
+    #[cfg(feature = "embassy-net")]
+    pub(crate) fn transmit_or_drop<R>(self, length: usize, f: impl FnOnce(&mut [u8]) -> R) -> R {
+        self.transmit_ring
+            .transmit_or_drop(length, f, self.ethernet_dma, self.error_counters)
+    }
+
+    /// Returns true, if a frame of the length can be transmitted right away
+    #[cfg(feature = "embassy-net")]
+    pub(crate) fn can_transmit(&self, length: usize) -> bool {
+        self.transmit_ring.can_transmit(length)
+    }
+
+    pub(crate) fn transmit<R>(
+        self,
+        length: usize,
+        f: impl FnOnce(&mut [u8]) -> R,
+    ) -> Result<(R, TransmitHandle), TransmitError> {
+        self.transmit_ring
+            .transmit_frame(length, f, self.ethernet_dma, self.error_counters)
+    }
+}
+
+/// A received frame
+///
+/// Its entries are handed back to the DMA, when it is dropped.
+pub struct Frame<'a> {
+    receive_frame: ReceiveFrame<'a>,
+    timestamping: bool,
+}
+
+impl<'a> Frame<'a> {
+    pub fn as_bytes(&self) -> &[u8] {
+        self.receive_frame.as_bytes()
+    }
+
+    pub fn as_bytes_mut(&mut self) -> &mut [u8] {
+        self.receive_frame.as_bytes_mut()
+    }
+
+    /// Returns the time, at which the frame has been received
+    ///
+    /// Returns None, if timestamping has not been enabled.
+    pub fn timestamp(&self) -> Option<Timestamp> {
+        if !self.timestamping {
+            return None;
+        }
+
+        self.receive_frame.timestamp()
+    }
+
+    /// Returns the VLAN tag of the frame, or None, if the frame is not tagged
+    pub fn vlan_tag(&self) -> Option<VlanTag> {
+        vlan::tag(self.as_bytes())
+    }
+
+    /// Removes the VLAN tag, and returns it with the untagged frame
+    pub fn strip_vlan_tag(&mut self) -> Option<(VlanTag, &mut [u8])> {
+        vlan::strip_tag(self.as_bytes_mut())
+    }
+}
+
+#[cfg(feature = "smoltcp")]
+impl<'a, 'r, 't, const N: usize> Device<'a> for EthernetDevice<'r, 't, N>
 where
     'r: 'a,
     't: 'a,
 {
-    type TxToken = TransmitToken<'a, 't>;
+    type TxToken = TransmitToken<'a, 't, N>;
     type RxToken = ReceiveToken<'a>;
 
     fn receive(&'a mut self) -> Option<(Self::RxToken, Self::TxToken)> {
-        let Self {
-            ref mut receive_ring,
-            ref mut transmit_ring,
-            ref ethernet_dma,
-            ..
-        } = self;
-
-        // Check if there is space in the receive ring
-        let receive_frame = receive_ring.receive_frame(ethernet_dma).ok()?;
+        let (receive_frame, transmitter) = self.receive_with_transmitter();
 
         Some((
-            ReceiveToken { receive_frame },
-            TransmitToken {
-                transmit_ring,
-                ethernet_dma,
+            ReceiveToken {
+                receive_frame: receive_frame?,
             },
+            TransmitToken { transmitter },
         ))
     }
 
     fn transmit(&'a mut self) -> Option<Self::TxToken> {
-        let Self {
-            ref mut transmit_ring,
-            ref ethernet_dma,
-            ..
-        } = self;
-
         Some(TransmitToken {
-            transmit_ring,
-            ethernet_dma,
+            transmitter: self.transmitter(),
         })
     }
 
     fn capabilities(&self) -> DeviceCapabilities {
         let mut result = DeviceCapabilities::default();
 
-        result.max_transmission_unit = MAX_TRANSMISSION_UNIT;
+        result.max_transmission_unit = self.max_transmission_unit();
         // Cycle stealing mode: Burst of a single byte to not starve the CPU from the system Bus
         result.max_burst_size = Some(1);
+        // The MAC inserts and checks the IPv4, TCP, UDP and ICMP checksums
+        result.checksum = ChecksumCapabilities::ignored();
 
         result
     }
 }
 
-pub struct TransmitToken<'a, 'b> {
-    transmit_ring: &'a mut TransmitRing<'b>,
-    ethernet_dma: &'a ETHERNET_DMA,
+#[cfg(feature = "smoltcp")]
+pub struct TransmitToken<'a, 'b, const N: usize> {
+    transmitter: Transmitter<'a, 'b, N>,
 }
 
-impl<'a, 'b> TxToken for TransmitToken<'a, 'b> {
+#[cfg(feature = "smoltcp")]
+impl<'a, 'b, const N: usize> TxToken for TransmitToken<'a, 'b, N> {
     fn consume<R, F>(
         self,
         _timestamp: smoltcp::time::Instant,
@@ -276,18 +723,21 @@ impl<'a, 'b> TxToken for TransmitToken<'a, 'b> {
     where
         F: FnOnce(&mut [u8]) -> smoltcp::Result<R>,
     {
-        match self.transmit_ring.transmit_frame(len, f, self.ethernet_dma) {
-            Ok(inner_result) => inner_result,
+        match self.transmitter.transmit(len, f) {
+            Ok((inner_result, _)) => inner_result,
             Err(TransmitError::BufferFull) => Err(Error::Exhausted),
             Err(TransmitError::FrameTooLong) => Err(Error::NotSupported),
+            Err(TransmitError::FrameTooShort) => Err(Error::Truncated),
         }
     }
 }
 
+#[cfg(feature = "smoltcp")]
 pub struct ReceiveToken<'a> {
     receive_frame: ReceiveFrame<'a>,
 }
 
+#[cfg(feature = "smoltcp")]
 impl<'a> RxToken for ReceiveToken<'a> {
     fn consume<R, F>(mut self, _timestamp: smoltcp::time::Instant, f: F) -> smoltcp::Result<R>
     where
@@ -0,0 +1,172 @@
+//! Driver for `embassy-net`
+//!
+//! The driver enables the interrupts of the device, so the `ETH` interrupt must call
+//! `ethernet::interrupt::on_interrupt`, which wakes `embassy-net`, when a frame has been received
+//! or transmitted.
+//!
+//! The transmit ring must hold a frame of the maximum transmission unit,
+//! e.g. with buffers of 256 bytes, it needs at least 6 entries, and a frame buffer of the MTU.
+//! Frames, which can still not be transmitted, are dropped and counted in the error counters.
+//!
+//! The PHY has no interrupt, so the link is only read, when the driver is polled.
+//! To notice a change of the link without any traffic, call `ethernet::interrupt::wake`
+//! periodically, e.g. once per second.
+
+use core::task::Context;
+
+use embassy_net_driver::{
+    Capabilities, Checksum, Driver, HardwareAddress, LinkState, RxToken, TxToken,
+};
+
+use super::{
+    device::{EthernetDevice, Transmitter},
+    interrupt,
+    phy::Phy,
+    pins::MdioPins,
+    receive::ReceiveFrame,
+    ring::DEFAULT_BUFFER_SIZE,
+};
+
+/// The Ethernet device, together with its PHY, as a driver for `embassy-net`
+pub struct EmbassyDriver<'r, 't, P, const N: usize = DEFAULT_BUFFER_SIZE> {
+    device: EthernetDevice<'r, 't, N>,
+    phy: P,
+    mdio_pins: MdioPins,
+}
+
+impl<'r, 't, P: Phy, const N: usize> EmbassyDriver<'r, 't, P, N> {
+    /// Creates the driver, and enables the interrupts of the device
+    ///
+    /// # Panics
+    ///
+    /// If the entries or the frame buffer of the transmit ring can not hold a frame
+    /// of the maximum transmission unit, because the driver could never transmit or receive a frame.
+    pub fn new(mut device: EthernetDevice<'r, 't, N>, phy: P, mdio_pins: MdioPins) -> Self {
+        let max_transmission_unit = device.max_transmission_unit();
+        assert!(
+            device.transmitter().fits(max_transmission_unit),
+            "The transmit ring must hold a frame of the maximum transmission unit"
+        );
+
+        device.listen();
+
+        Self {
+            device,
+            phy,
+            mdio_pins,
+        }
+    }
+
+    /// Gives access to the device, e.g. to read its counters
+    pub fn device(&mut self) -> &mut EthernetDevice<'r, 't, N> {
+        &mut self.device
+    }
+
+    pub fn release(self) -> (EthernetDevice<'r, 't, N>, P, MdioPins) {
+        (self.device, self.phy, self.mdio_pins)
+    }
+}
+
+impl<'r, 't, P: Phy, const N: usize> Driver for EmbassyDriver<'r, 't, P, N> {
+    type RxToken<'a>
+        = ReceiveToken<'a>
+    where
+        Self: 'a;
+    type TxToken<'a>
+        = TransmitToken<'a, 't, N>
+    where
+        Self: 'a;
+
+    fn receive(&mut self, cx: &mut Context) -> Option<(Self::RxToken<'_>, Self::TxToken<'_>)> {
+        // Registered before the rings are checked, so that no interrupt is missed
+        interrupt::register_waker(cx.waker());
+
+        // The transmit token must not fail, so it requires entries for a frame of any length.
+        // This is checked first, because a received frame would be dropped.
+        let max_frame_length = self.device.max_transmission_unit();
+        if !self.device.transmitter().can_transmit(max_frame_length) {
+            return None;
+        }
+
+        let (receive_frame, transmitter) = self.device.receive_with_transmitter();
+
+        Some((
+            ReceiveToken {
+                receive_frame: receive_frame?,
+            },
+            TransmitToken { transmitter },
+        ))
+    }
+
+    fn transmit(&mut self, cx: &mut Context) -> Option<Self::TxToken<'_>> {
+        interrupt::register_waker(cx.waker());
+
+        let max_frame_length = self.device.max_transmission_unit();
+        let transmitter = self.device.transmitter();
+
+        if !transmitter.can_transmit(max_frame_length) {
+            return None;
+        }
+
+        Some(TransmitToken { transmitter })
+    }
+
+    fn link_state(&mut self, cx: &mut Context) -> LinkState {
+        interrupt::register_waker(cx.waker());
+
+        // If the PHY can not be read, the last known link is kept
+        let _ = self.device.poll_link(&mut self.phy, &self.mdio_pins);
+
+        match self.device.link() {
+            Some(_) => LinkState::Up,
+            None => LinkState::Down,
+        }
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        let mut result = Capabilities::default();
+
+        result.max_transmission_unit = self.device.max_transmission_unit();
+        // Cycle stealing mode: Burst of a single byte to not starve the CPU from the system Bus
+        result.max_burst_size = Some(1);
+        // The MAC inserts and checks the IPv4, TCP, UDP and ICMP checksums
+        result.checksum.ipv4 = Checksum::None;
+        result.checksum.udp = Checksum::None;
+        result.checksum.tcp = Checksum::None;
+        result.checksum.icmpv4 = Checksum::None;
+        result.checksum.icmpv6 = Checksum::None;
+
+        result
+    }
+
+    fn hardware_address(&self) -> HardwareAddress {
+        HardwareAddress::Ethernet(self.device.mac_address())
+    }
+}
+
+pub struct ReceiveToken<'a> {
+    receive_frame: ReceiveFrame<'a>,
+}
+
+impl<'a> RxToken for ReceiveToken<'a> {
+    fn consume<R, F>(mut self, f: F) -> R
+    where
+        F: FnOnce(&mut [u8]) -> R,
+    {
+        f(self.receive_frame.as_bytes_mut())
+    }
+}
+
+pub struct TransmitToken<'a, 'b, const N: usize> {
+    transmitter: Transmitter<'a, 'b, N>,
+}
+
+impl<'a, 'b, const N: usize> TxToken for TransmitToken<'a, 'b, N> {
+    fn consume<R, F>(self, len: usize, f: F) -> R
+    where
+        F: FnOnce(&mut [u8]) -> R,
+    {
+        // A frame, which is longer than the MTU, or shorter than the header, is dropped
+        self.transmitter.transmit_or_drop(len, f)
+    }
+}
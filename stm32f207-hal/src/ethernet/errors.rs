@@ -0,0 +1,106 @@
+//! Errors of received and transmitted frames, and the counters of the driver
+
+/// The error bits of a received frame, decoded from its descriptor
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ReceiveFrameErrors {
+    pub crc: bool,
+    /// The frame had an odd number of nibbles
+    pub dribble_bit: bool,
+    /// The PHY signalled a receive error
+    pub receive: bool,
+    /// The frame was longer than 2048 bytes
+    pub watchdog_timeout: bool,
+    /// A collision occurred after the first 64 bytes in half duplex mode
+    pub late_collision: bool,
+    /// The receive FIFO overflowed, so the frame is incomplete
+    pub overflow: bool,
+    /// The length field does not match the length of the frame
+    pub length: bool,
+    /// The frame did not fit into the entries, which were owned by the DMA
+    pub descriptor: bool,
+    pub ip_header_checksum: bool,
+    /// The TCP, UDP or ICMP checksum is wrong
+    pub ip_payload_checksum: bool,
+}
+
+/// The error bits of a transmitted frame, decoded from its descriptor
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct TransmitFrameErrors {
+    /// The transmission took longer than 2048 bytes
+    pub jabber_timeout: bool,
+    /// The frame was flushed from the FIFO on request of the driver
+    pub frame_flushed: bool,
+    /// The checksum could not be inserted, because the TCP, UDP or ICMP header was invalid
+    pub ip_payload_checksum: bool,
+    pub loss_of_carrier: bool,
+    pub no_carrier: bool,
+    /// A collision occurred after the first 64 bytes in half duplex mode
+    pub late_collision: bool,
+    /// The transmission was aborted after 16 collisions in half duplex mode
+    pub excessive_collisions: bool,
+    /// The transmission was deferred for more than 24288 bit times in half duplex mode
+    pub excessive_deferral: bool,
+    /// The DMA could not provide the frame to the MAC in time
+    pub underflow: bool,
+    /// The checksum could not be inserted, because the IP header was invalid
+    pub ip_header_checksum: bool,
+}
+
+/// Counters of the frames, which were lost or had errors, and of the recoveries of the DMA
+///
+/// All counters roll over to zero.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ErrorCounters {
+    /// Received frames, which were dropped by the DMA,
+    /// because no entry was available or the receive FIFO overflowed
+    pub dropped_frames: u32,
+    /// Frames to transmit, which were dropped by the embassy-net driver,
+    /// because they did not fit into the transmit ring
+    pub dropped_transmit_frames: u32,
+    /// Received frames, whose segments filled the whole ring without a last segment,
+    /// or which spanned several entries, but were longer than the frame buffer of the ring
+    pub truncated_frames: u32,
+    /// Received frames with errors, see `last_receive_errors`
+    pub receive_errors: u32,
+    /// Transmitted frames with errors, see `last_transmit_errors`
+    pub transmit_errors: u32,
+    /// Number of times the reception was resumed, after the DMA ran out of entries
+    pub receive_buffer_unavailable: u32,
+    /// Number of times the transmission was resumed, after an underflow
+    pub transmit_underflows: u32,
+    pub last_receive_errors: Option<ReceiveFrameErrors>,
+    pub last_transmit_errors: Option<TransmitFrameErrors>,
+}
+
+impl ErrorCounters {
+    pub(crate) fn count_dropped_frames(&mut self, frames: u32) {
+        self.dropped_frames = self.dropped_frames.wrapping_add(frames);
+    }
+
+    #[cfg(feature = "embassy-net")]
+    pub(crate) fn count_dropped_transmit_frame(&mut self) {
+        self.dropped_transmit_frames = self.dropped_transmit_frames.wrapping_add(1);
+    }
+
+    pub(crate) fn count_truncated_frame(&mut self) {
+        self.truncated_frames = self.truncated_frames.wrapping_add(1);
+    }
+
+    pub(crate) fn count_receive_buffer_unavailable(&mut self) {
+        self.receive_buffer_unavailable = self.receive_buffer_unavailable.wrapping_add(1);
+    }
+
+    pub(crate) fn count_transmit_underflow(&mut self) {
+        self.transmit_underflows = self.transmit_underflows.wrapping_add(1);
+    }
+
+    pub(crate) fn count_receive_errors(&mut self, errors: ReceiveFrameErrors) {
+        self.receive_errors = self.receive_errors.wrapping_add(1);
+        self.last_receive_errors = Some(errors);
+    }
+
+    pub(crate) fn count_transmit_errors(&mut self, errors: TransmitFrameErrors) {
+        self.transmit_errors = self.transmit_errors.wrapping_add(1);
+        self.last_transmit_errors = Some(errors);
+    }
+}
@@ -0,0 +1,139 @@
+//! Filtering of received frames by the MAC
+
+use stm32f2::stm32f217::ETHERNET_MAC;
+
+use super::vlan::VlanTag;
+
+/// Selects the multicast frames, which pass the filter
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MulticastFilter {
+    /// Only frames, whose destination address hashes to a bit set in the hash table
+    ///
+    /// Addresses are added to the hash table with `EthernetDevice::add_multicast_address`.
+    /// Because of hash collisions, some frames to other addresses may pass as well.
+    HashTable,
+    /// All multicast frames
+    PassAll,
+}
+
+/// Selects the frames, which are received by the MAC
+///
+/// Unicast frames always have to match the MAC address of the device,
+/// unless the filter is promiscuous.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FrameFilter {
+    /// Receive all frames, regardless of their destination address
+    pub promiscuous: bool,
+    /// Receive broadcast frames
+    pub broadcast: bool,
+    pub multicast: MulticastFilter,
+}
+
+impl Default for FrameFilter {
+    /// Perfect unicast filtering, broadcast frames, and hash table filtering of multicast frames
+    fn default() -> Self {
+        Self {
+            promiscuous: false,
+            broadcast: true,
+            multicast: MulticastFilter::HashTable,
+        }
+    }
+}
+
+impl FrameFilter {
+    pub(crate) fn apply(&self, ethernet_mac: &ETHERNET_MAC) {
+        ethernet_mac.macffr.write(|w| {
+            w.pm()
+                .bit(self.promiscuous)
+                .bfd()
+                .bit(!self.broadcast)
+                .pam()
+                .bit(self.multicast == MulticastFilter::PassAll)
+                .hm()
+                .bit(self.multicast == MulticastFilter::HashTable)
+        });
+    }
+}
+
+/// Selects the VLAN frames, which are received
+///
+/// The MAC only marks the matching frames in their receive status,
+/// so the driver drops the tagged frames, which do not match.
+/// Frames without a tag are not affected.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum VlanFilter {
+    /// Only frames, whose 12 bit VLAN identifier matches
+    ///
+    /// The identifier 0 disables the comparison, so that all VLAN frames match.
+    Identifier(u16),
+    /// Only frames, whose whole 16 bit tag matches, including the priority and drop eligible bit
+    Tag(VlanTag),
+}
+
+/// Programs the VLAN tag register, None matches all VLAN frames
+pub(crate) fn set_vlan_filter(ethernet_mac: &ETHERNET_MAC, vlan_filter: Option<VlanFilter>) {
+    let (tag, twelve_bit) = match vlan_filter {
+        Some(VlanFilter::Identifier(identifier)) => (identifier & 0xFFF, true),
+        Some(VlanFilter::Tag(tag)) => (tag.tag_control(), false),
+        None => (0, false),
+    };
+
+    ethernet_mac
+        .macvlantr
+        .write(|w| unsafe { w.vlanti().bits(tag).vlantc().bit(twelve_bit) });
+}
+
+/// Programs the MAC address, which is used for perfect unicast filtering and pause frames
+pub(crate) fn set_mac_address(ethernet_mac: &ETHERNET_MAC, mac_address: [u8; 6]) {
+    let [a0, a1, a2, a3, a4, a5] = mac_address;
+
+    // The low register must be written last, because writing it latches the whole address
+    ethernet_mac
+        .maca0hr
+        .write(|w| unsafe { w.maca0h().bits(u16::from_le_bytes([a4, a5])) });
+    ethernet_mac
+        .maca0lr
+        .write(|w| unsafe { w.maca0l().bits(u32::from_le_bytes([a0, a1, a2, a3])) });
+}
+
+/// Sets the bit for the address in the multicast hash table
+pub(crate) fn add_to_hash_table(ethernet_mac: &ETHERNET_MAC, address: [u8; 6]) {
+    let index = hash_table_index(address);
+    let bit = 1 << (index & 0x1F);
+
+    if index & 0x20 != 0 {
+        ethernet_mac
+            .machthr
+            .modify(|r, w| unsafe { w.hth().bits(r.hth().bits() | bit) });
+    } else {
+        ethernet_mac
+            .machtlr
+            .modify(|r, w| unsafe { w.htl().bits(r.htl().bits() | bit) });
+    }
+}
+
+pub(crate) fn clear_hash_table(ethernet_mac: &ETHERNET_MAC) {
+    ethernet_mac.machthr.write(|w| unsafe { w.hth().bits(0) });
+    ethernet_mac.machtlr.write(|w| unsafe { w.htl().bits(0) });
+}
+
+/// Returns the index into the 64 bit hash table, for the destination address
+///
+/// The MAC uses the upper 6 bits of the bit reversed Ethernet CRC-32 of the address.
+fn hash_table_index(address: [u8; 6]) -> u32 {
+    let mut crc: u32 = 0xFFFF_FFFF;
+
+    for byte in address {
+        crc ^= byte as u32;
+
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xEDB8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+
+    (!crc).reverse_bits() >> 26
+}
@@ -0,0 +1,129 @@
+//! Interrupt driven reception and transmission
+//!
+//! After `EthernetDevice::listen` has been called, the `ETH` interrupt must call [`on_interrupt`]:
+//!
+//! ```ignore
+//! #[interrupt]
+//! fn ETH() {
+//!     stm32f207_hal::ethernet::interrupt::on_interrupt();
+//! }
+//! ```
+//!
+//! The main loop can then sleep with [`sleep`] or [`wait_for_frame`],
+//! and async code can register a waker with [`register_waker`].
+
+use core::{
+    sync::atomic::{AtomicBool, Ordering},
+    task::Waker,
+};
+
+use cortex_m::{asm, interrupt, register::primask};
+use stm32f2::stm32f217::{ETHERNET_DMA, ETHERNET_PTP};
+
+use crate::interrupt_free_cell::InterruptFreeCell;
+
+static FRAME_RECEIVED: AtomicBool = AtomicBool::new(false);
+static TARGET_TIME_REACHED: AtomicBool = AtomicBool::new(false);
+static WAKER: InterruptFreeCell<Option<Waker>> = InterruptFreeCell::new(None);
+
+/// The events, which caused an interrupt
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct InterruptEvents {
+    /// A frame has been received
+    pub received: bool,
+    /// A frame has been transmitted
+    pub transmitted: bool,
+    /// The PTP clock has reached the target time
+    pub target_time_reached: bool,
+}
+
+/// Handles the `ETH` interrupt
+///
+/// Acknowledges the interrupt, flags received frames, and wakes the registered waker.
+/// A reached target time is latched as well, so that `PtpClock::is_target_time_reached`
+/// still reports it.
+pub fn on_interrupt() -> InterruptEvents {
+    // Safety: Only the status registers are accessed. The DMA flags are cleared by writing ones,
+    // writing zeros has no effect, so the other flags used by the EthernetDevice are not changed.
+    // Reading the PTP status clears its target time flag, which is latched for the PtpClock.
+    let ethernet_dma = unsafe { &*ETHERNET_DMA::ptr() };
+    let ethernet_ptp = unsafe { &*ETHERNET_PTP::ptr() };
+
+    let status = ethernet_dma.dmasr.read();
+    let mut events = InterruptEvents {
+        received: status.rs().bit_is_set(),
+        transmitted: status.ts().bit_is_set(),
+        target_time_reached: false,
+    };
+
+    if status.tsts().bit_is_set() {
+        // Reading the PTP status clears the time stamp trigger
+        events.target_time_reached = ethernet_ptp.ptptssr.read().tsttr().bit_is_set();
+
+        if events.target_time_reached {
+            TARGET_TIME_REACHED.store(true, Ordering::Release);
+        }
+    }
+
+    ethernet_dma
+        .dmasr
+        .write(|w| w.nis().set_bit().rs().set_bit().ts().set_bit());
+
+    if events.received {
+        FRAME_RECEIVED.store(true, Ordering::Release);
+    }
+
+    wake();
+
+    events
+}
+
+/// Registers the waker, which is woken by the next interrupt
+pub fn register_waker(waker: &Waker) {
+    WAKER.set(Some(waker.clone()));
+}
+
+/// Wakes the registered waker, e.g. periodically, so that the link is polled
+pub fn wake() {
+    if let Some(waker) = WAKER.take() {
+        waker.wake();
+    }
+}
+
+/// Returns true, if a frame has been received since the last call, and clears the flag
+pub fn take_frame_received() -> bool {
+    FRAME_RECEIVED.swap(false, Ordering::AcqRel)
+}
+
+/// Returns true, if the interrupt has seen the target time being reached, and clears the flag
+pub(crate) fn take_target_time_reached() -> bool {
+    TARGET_TIME_REACHED.swap(false, Ordering::AcqRel)
+}
+
+/// Sleeps with WFI until the next interrupt, unless a frame has already been received
+///
+/// Returns true, if a frame has been received since the last call.
+/// Any interrupt ends the sleep, e.g. the SysTick of the system clock.
+pub fn sleep() -> bool {
+    let interrupts_enabled = primask::read().is_active();
+
+    // The flag is checked with interrupts disabled, so that an interrupt can not slip in
+    // between the check and WFI. WFI still wakes up on the pending interrupt.
+    interrupt::disable();
+
+    if !FRAME_RECEIVED.load(Ordering::Acquire) {
+        asm::wfi();
+    }
+
+    if interrupts_enabled {
+        // Safety: Interrupts were enabled before
+        unsafe { interrupt::enable() };
+    }
+
+    take_frame_received()
+}
+
+/// Sleeps until a frame has been received
+pub fn wait_for_frame() {
+    while !sleep() {}
+}
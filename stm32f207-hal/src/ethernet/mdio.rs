@@ -0,0 +1,82 @@
+//! Access to the registers of the PHY over the station management interface (MDIO)
+
+use stm32f2::stm32f217::ETHERNET_MAC;
+
+/// Number of times the busy flag is polled, before a transfer is considered failed
+///
+/// A transfer takes 64 MDC cycles, which are at most 62 * 64 HCLK cycles.
+const MDIO_TIMEOUT_POLLS: u32 = 100_000;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MdioError {
+    /// The previous or the current transfer did not finish in time
+    Timeout,
+}
+
+/// The station management interface, borrowed from the Ethernet MAC
+///
+/// Created with `EthernetDevice::mdio`.
+pub struct Mdio<'a> {
+    ethernet_mac: &'a ETHERNET_MAC,
+}
+
+impl<'a> Mdio<'a> {
+    pub(crate) fn new(ethernet_mac: &'a ETHERNET_MAC) -> Self {
+        Self { ethernet_mac }
+    }
+
+    /// Reads the register of the PHY with the given address
+    pub fn read(&mut self, phy_address: u8, register: u8) -> Result<u16, MdioError> {
+        self.wait_until_idle()?;
+        self.start_transfer(phy_address, register, false);
+        self.wait_until_idle()?;
+
+        Ok(self.ethernet_mac.macmiidr.read().md().bits())
+    }
+
+    /// Writes the register of the PHY with the given address
+    pub fn write(&mut self, phy_address: u8, register: u8, value: u16) -> Result<(), MdioError> {
+        self.wait_until_idle()?;
+
+        self.ethernet_mac
+            .macmiidr
+            .write(|w| unsafe { w.md().bits(value) });
+        self.start_transfer(phy_address, register, true);
+
+        self.wait_until_idle()
+    }
+
+    /// Sets the bits of the mask in the register, and keeps all others
+    pub fn set_bits(&mut self, phy_address: u8, register: u8, mask: u16) -> Result<(), MdioError> {
+        let value = self.read(phy_address, register)?;
+
+        self.write(phy_address, register, value | mask)
+    }
+
+    fn start_transfer(&mut self, phy_address: u8, register: u8, write: bool) {
+        assert!(phy_address < 32, "The PHY address must be less than 32");
+        assert!(register < 32, "The PHY register must be less than 32");
+
+        // The clock range is kept as configured by the EthernetDevice
+        self.ethernet_mac.macmiiar.modify(|_, w| unsafe {
+            w.pa()
+                .bits(phy_address)
+                .mr()
+                .bits(register)
+                .mw()
+                .bit(write)
+                .mb()
+                .set_bit()
+        });
+    }
+
+    fn wait_until_idle(&self) -> Result<(), MdioError> {
+        for _ in 0..MDIO_TIMEOUT_POLLS {
+            if self.ethernet_mac.macmiiar.read().mb().bit_is_clear() {
+                return Ok(());
+            }
+        }
+
+        Err(MdioError::Timeout)
+    }
+}
@@ -3,9 +3,27 @@
 
 mod descriptor;
 pub mod device;
+#[cfg(feature = "embassy-net")]
+pub mod embassy;
+pub mod errors;
+pub mod filter;
+pub mod interrupt;
+pub mod mdio;
+pub mod phy;
 pub mod pins;
+pub mod ptp;
 mod receive;
 pub mod ring;
+pub mod statistics;
 mod transmit;
+pub mod vlan;
+
+pub use transmit::{TransmitError, TransmitHandle};
 
 const MAX_TRANSMISSION_UNIT: usize = 1522; // VLAN Frame max size
+const VLAN_TAG_LENGTH: usize = 4;
+// Frame check sequence, the CRC at the end of the frame
+const FCS_LENGTH: usize = 4;
+// Size of each of the transmit and receive FIFOs of the MAC.
+// The store-and-forward mode and the checksum offload require a whole frame in the FIFO.
+const FIFO_SIZE: usize = 2048;
@@ -0,0 +1,291 @@
+//! Drivers for IEEE 802.3 Clause 22 PHYs, accessed over MDIO
+
+use super::mdio::{Mdio, MdioError};
+
+/// Addresses of the basic registers, which are defined by IEEE 802.3 Clause 22
+pub mod registers {
+    /// Basic mode control register
+    pub const BMCR: u8 = 0x00;
+    /// Basic mode status register
+    pub const BMSR: u8 = 0x01;
+    /// PHY identifier register 1
+    pub const PHYIDR1: u8 = 0x02;
+    /// PHY identifier register 2
+    pub const PHYIDR2: u8 = 0x03;
+    /// Autonegotiation advertisement register
+    pub const ANAR: u8 = 0x04;
+    /// Autonegotiation link partner ability register
+    pub const ANLPAR: u8 = 0x05;
+}
+
+use registers::*;
+
+const BMCR_RESET: u16 = 1 << 15;
+const BMCR_SPEED_100: u16 = 1 << 13;
+const BMCR_AUTONEGOTIATION_ENABLE: u16 = 1 << 12;
+const BMCR_RESTART_AUTONEGOTIATION: u16 = 1 << 9;
+const BMCR_FULL_DUPLEX: u16 = 1 << 8;
+
+const BMSR_AUTONEGOTIATION_COMPLETE: u16 = 1 << 5;
+const BMSR_LINK_STATUS: u16 = 1 << 2;
+
+const ANAR_100_FULL_DUPLEX: u16 = 1 << 8;
+const ANAR_100_HALF_DUPLEX: u16 = 1 << 7;
+const ANAR_10_FULL_DUPLEX: u16 = 1 << 6;
+const ANAR_10_HALF_DUPLEX: u16 = 1 << 5;
+// Selector field for IEEE 802.3
+const ANAR_SELECTOR_802_3: u16 = 0b00001;
+
+/// Number of times the reset bit is polled, before the reset is considered failed
+const RESET_TIMEOUT_POLLS: u32 = 10_000;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PhyError {
+    Mdio(MdioError),
+    /// The PHY did not finish its reset in time
+    ResetTimeout,
+}
+
+impl From<MdioError> for PhyError {
+    fn from(error: MdioError) -> Self {
+        PhyError::Mdio(error)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Speed {
+    Mbps10,
+    Mbps100,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Duplex {
+    Half,
+    Full,
+}
+
+/// Speed and duplex mode of an established link
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Link {
+    pub speed: Speed,
+    pub duplex: Duplex,
+}
+
+/// A Clause 22 PHY
+///
+/// The default implementations only use the basic registers,
+/// drivers for specific PHYs may use their vendor specific registers instead.
+pub trait Phy {
+    /// Returns the address of the PHY on the MDIO bus
+    fn address(&self) -> u8;
+
+    /// Resets the PHY, and waits until the reset has completed
+    fn reset(&mut self, mdio: &mut Mdio) -> Result<(), PhyError> {
+        mdio.write(self.address(), BMCR, BMCR_RESET)?;
+
+        for _ in 0..RESET_TIMEOUT_POLLS {
+            if mdio.read(self.address(), BMCR)? & BMCR_RESET == 0 {
+                return Ok(());
+            }
+        }
+
+        Err(PhyError::ResetTimeout)
+    }
+
+    /// Returns the organizationally unique identifier, model and revision of the PHY
+    fn identifier(&mut self, mdio: &mut Mdio) -> Result<u32, PhyError> {
+        let high = mdio.read(self.address(), PHYIDR1)?;
+        let low = mdio.read(self.address(), PHYIDR2)?;
+
+        Ok((high as u32) << 16 | low as u32)
+    }
+
+    /// Advertises all 10 and 100 Mbit/s modes, and (re)starts the autonegotiation
+    fn start_autonegotiation(&mut self, mdio: &mut Mdio) -> Result<(), PhyError> {
+        mdio.write(
+            self.address(),
+            ANAR,
+            ANAR_100_FULL_DUPLEX
+                | ANAR_100_HALF_DUPLEX
+                | ANAR_10_FULL_DUPLEX
+                | ANAR_10_HALF_DUPLEX
+                | ANAR_SELECTOR_802_3,
+        )?;
+
+        mdio.set_bits(
+            self.address(),
+            BMCR,
+            BMCR_AUTONEGOTIATION_ENABLE | BMCR_RESTART_AUTONEGOTIATION,
+        )?;
+
+        Ok(())
+    }
+
+    /// Disables the autonegotiation, and forces the given speed and duplex mode
+    fn force_link(&mut self, mdio: &mut Mdio, link: Link) -> Result<(), PhyError> {
+        let mut control = 0;
+
+        if link.speed == Speed::Mbps100 {
+            control |= BMCR_SPEED_100;
+        }
+
+        if link.duplex == Duplex::Full {
+            control |= BMCR_FULL_DUPLEX;
+        }
+
+        mdio.write(self.address(), BMCR, control)?;
+
+        Ok(())
+    }
+
+    /// Returns true, if the link is up
+    fn is_link_up(&mut self, mdio: &mut Mdio) -> Result<bool, PhyError> {
+        // The link status is latched low, so the first read returns whether the link was lost since
+        // the last read, and the second one the current status
+        let _ = mdio.read(self.address(), BMSR)?;
+        let status = mdio.read(self.address(), BMSR)?;
+
+        Ok(status & BMSR_LINK_STATUS != 0)
+    }
+
+    /// Returns the speed and duplex mode of the link, or None, if the link is down
+    ///
+    /// With autonegotiation, the link is only reported after the autonegotiation has completed.
+    fn link(&mut self, mdio: &mut Mdio) -> Result<Option<Link>, PhyError> {
+        if !self.is_link_up(mdio)? {
+            return Ok(None);
+        }
+
+        let control = mdio.read(self.address(), BMCR)?;
+
+        if control & BMCR_AUTONEGOTIATION_ENABLE == 0 {
+            return Ok(Some(Link {
+                speed: if control & BMCR_SPEED_100 != 0 {
+                    Speed::Mbps100
+                } else {
+                    Speed::Mbps10
+                },
+                duplex: if control & BMCR_FULL_DUPLEX != 0 {
+                    Duplex::Full
+                } else {
+                    Duplex::Half
+                },
+            }));
+        }
+
+        let status = mdio.read(self.address(), BMSR)?;
+        if status & BMSR_AUTONEGOTIATION_COMPLETE == 0 {
+            return Ok(None);
+        }
+
+        // The link uses the best mode, which is supported by both sides
+        let advertised = mdio.read(self.address(), ANAR)?;
+        let partner = mdio.read(self.address(), ANLPAR)?;
+        let common = advertised & partner;
+
+        let link = if common & ANAR_100_FULL_DUPLEX != 0 {
+            Link {
+                speed: Speed::Mbps100,
+                duplex: Duplex::Full,
+            }
+        } else if common & ANAR_100_HALF_DUPLEX != 0 {
+            Link {
+                speed: Speed::Mbps100,
+                duplex: Duplex::Half,
+            }
+        } else if common & ANAR_10_FULL_DUPLEX != 0 {
+            Link {
+                speed: Speed::Mbps10,
+                duplex: Duplex::Full,
+            }
+        } else {
+            Link {
+                speed: Speed::Mbps10,
+                duplex: Duplex::Half,
+            }
+        };
+
+        Ok(Some(link))
+    }
+}
+
+/// A PHY, which is only accessed through the basic registers
+pub struct GenericPhy {
+    address: u8,
+}
+
+impl GenericPhy {
+    pub fn new(address: u8) -> Self {
+        Self { address }
+    }
+}
+
+impl Phy for GenericPhy {
+    fn address(&self) -> u8 {
+        self.address
+    }
+}
+
+/// The Texas Instruments DP83848, which is mounted on the evaluation board
+pub struct Dp83848 {
+    address: u8,
+}
+
+impl Dp83848 {
+    /// Address of the PHY on the evaluation board
+    pub const EVAL_BOARD_ADDRESS: u8 = 0x01;
+
+    /// Value of the identifier, without the revision in the lowest 4 bits
+    pub const IDENTIFIER: u32 = 0x2000_5C90;
+
+    /// PHY status register
+    const PHYSTS: u8 = 0x10;
+
+    const PHYSTS_AUTONEGOTIATION_COMPLETE: u16 = 1 << 4;
+    const PHYSTS_FULL_DUPLEX: u16 = 1 << 2;
+    const PHYSTS_SPEED_10: u16 = 1 << 1;
+    const PHYSTS_LINK_STATUS: u16 = 1 << 0;
+
+    pub fn new(address: u8) -> Self {
+        Self { address }
+    }
+
+    /// Returns true, if the PHY at the address is a DP83848
+    pub fn is_present(&mut self, mdio: &mut Mdio) -> Result<bool, PhyError> {
+        Ok(self.identifier(mdio)? & !0xF == Self::IDENTIFIER)
+    }
+}
+
+impl Phy for Dp83848 {
+    fn address(&self) -> u8 {
+        self.address
+    }
+
+    /// Returns the speed and duplex mode of the link, or None, if the link is down
+    ///
+    /// The DP83848 reports the resolved mode in its status register.
+    fn link(&mut self, mdio: &mut Mdio) -> Result<Option<Link>, PhyError> {
+        let status = mdio.read(self.address, Self::PHYSTS)?;
+        let control = mdio.read(self.address, BMCR)?;
+
+        let autonegotiation_pending = control & BMCR_AUTONEGOTIATION_ENABLE != 0
+            && status & Self::PHYSTS_AUTONEGOTIATION_COMPLETE == 0;
+
+        if status & Self::PHYSTS_LINK_STATUS == 0 || autonegotiation_pending {
+            return Ok(None);
+        }
+
+        Ok(Some(Link {
+            speed: if status & Self::PHYSTS_SPEED_10 != 0 {
+                Speed::Mbps10
+            } else {
+                Speed::Mbps100
+            },
+            duplex: if status & Self::PHYSTS_FULL_DUPLEX != 0 {
+                Duplex::Full
+            } else {
+                Duplex::Half
+            },
+        }))
+    }
+}
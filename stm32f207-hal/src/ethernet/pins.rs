@@ -20,7 +20,78 @@ pub struct MiiPins {
     pub receive_er: Pin<Alternate<PushPull, 11>, 'I', 10>,
 }
 
-pub trait EthernetPins: Sealed {}
+/// The pins of the reduced media independent interface (RMII)
+///
+/// The transmit pins may be mapped to port B or port G, see the pin traits.
+/// The 50 MHz reference clock is an input on `reference_clk`.
+pub struct RmiiPins<TXEN, TXD0, TXD1> {
+    pub reference_clk: Pin<Alternate<PushPull, 11>, 'A', 1>,
+    pub crs_dv: Pin<Alternate<PushPull, 11>, 'A', 7>,
+    pub transmit_en: TXEN,
+    pub transmit_d0: TXD0,
+    pub transmit_d1: TXD1,
+    pub receive_d0: Pin<Alternate<PushPull, 11>, 'C', 4>,
+    pub receive_d1: Pin<Alternate<PushPull, 11>, 'C', 5>,
+}
+
+/// Pins, which can be used as RMII TX_EN
+pub trait RmiiTransmitEnPin: Sealed {}
+/// Pins, which can be used as RMII TXD0
+pub trait RmiiTransmitD0Pin: Sealed {}
+/// Pins, which can be used as RMII TXD1
+pub trait RmiiTransmitD1Pin: Sealed {}
+
+macro_rules! rmii_pins {
+    ($($trait:ident: [$(($port:literal, $pin:literal)),+],)+) => {
+        $(
+            $(
+                impl Sealed for Pin<Alternate<PushPull, 11>, $port, $pin> {}
+                impl $trait for Pin<Alternate<PushPull, 11>, $port, $pin> {}
+            )+
+        )+
+    };
+}
+
+rmii_pins! {
+    RmiiTransmitEnPin: [('B', 11), ('G', 11)],
+    RmiiTransmitD0Pin: [('B', 12), ('G', 13)],
+    RmiiTransmitD1Pin: [('B', 13), ('G', 14)],
+}
+
+pub trait EthernetPins: Sealed {
+    /// True for the reduced media independent interface, false for the media independent interface
+    #[doc(hidden)]
+    const RMII: bool;
+
+    #[doc(hidden)]
+    fn is_rmii(&self) -> bool {
+        Self::RMII
+    }
+}
 
 impl Sealed for MiiPins {}
-impl EthernetPins for MiiPins {}
+impl EthernetPins for MiiPins {
+    const RMII: bool = false;
+}
+
+impl<TXEN, TXD0, TXD1> Sealed for RmiiPins<TXEN, TXD0, TXD1>
+where
+    TXEN: RmiiTransmitEnPin,
+    TXD0: RmiiTransmitD0Pin,
+    TXD1: RmiiTransmitD1Pin,
+{
+}
+impl<TXEN, TXD0, TXD1> EthernetPins for RmiiPins<TXEN, TXD0, TXD1>
+where
+    TXEN: RmiiTransmitEnPin,
+    TXD0: RmiiTransmitD0Pin,
+    TXD1: RmiiTransmitD1Pin,
+{
+    const RMII: bool = true;
+}
+
+/// The pins of the station management interface, used to access the registers of the PHY
+pub struct MdioPins {
+    pub mdc: Pin<Alternate<PushPull, 11>, 'C', 1>,
+    pub mdio: Pin<Alternate<PushPull, 11>, 'A', 2>,
+}
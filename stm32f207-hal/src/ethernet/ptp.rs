@@ -0,0 +1,245 @@
+//! IEEE 1588 precision time protocol (PTP) clock and timestamps
+//!
+//! The PTP clock counts seconds and nanoseconds, driven by HCLK.
+//! Its frequency can be fine adjusted with the addend register, e.g. by a PTP servo.
+//! When timestamping is enabled, the MAC takes a snapshot of the clock for every frame,
+//! which is stored in the enhanced descriptor of the frame.
+
+use stm32f2::stm32f217::{ethernet_ptp::ptptscr, ETHERNET_MAC, ETHERNET_PTP};
+
+use super::interrupt;
+use crate::time::Hertz;
+
+const NANOS_PER_SECOND: u32 = 1_000_000_000;
+
+/// Number of times an update flag is polled, before the update is considered failed
+///
+/// An update is taken over within a few cycles of the PTP clock.
+const PTP_TIMEOUT_POLLS: u32 = 100_000;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PtpError {
+    /// The time or the addend was not taken over in time, e.g. because the clock is not running
+    Timeout,
+}
+
+/// A time of the PTP clock
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Timestamp {
+    pub seconds: u32,
+    /// Always less than one second
+    pub nanoseconds: u32,
+}
+
+impl Timestamp {
+    pub const fn new(seconds: u32, nanoseconds: u32) -> Self {
+        Self {
+            seconds: seconds + nanoseconds / NANOS_PER_SECOND,
+            nanoseconds: nanoseconds % NANOS_PER_SECOND,
+        }
+    }
+
+    pub const fn from_nanos(nanos: u64) -> Self {
+        Self {
+            seconds: (nanos / NANOS_PER_SECOND as u64) as u32,
+            nanoseconds: (nanos % NANOS_PER_SECOND as u64) as u32,
+        }
+    }
+
+    pub const fn as_nanos(&self) -> u64 {
+        self.seconds as u64 * NANOS_PER_SECOND as u64 + self.nanoseconds as u64
+    }
+
+    /// Creates the timestamp from the high and low words of a snapshot
+    pub(crate) const fn from_registers(high: u32, low: u32) -> Self {
+        // With the digital rollover, the low word counts nanoseconds
+        Self {
+            seconds: high,
+            nanoseconds: low & 0x7FFF_FFFF,
+        }
+    }
+}
+
+/// Returns the subsecond increment and the addend for the nominal frequency of the clock
+///
+/// The accumulator adds the addend at every HCLK cycle,
+/// and its overflows advance the clock by the subsecond increment.
+fn increment_and_addend(hclk: Hertz) -> (u8, u32) {
+    // The overflow frequency must stay below HCLK, to leave room for the fine adjustment
+    let increment = NANOS_PER_SECOND / hclk.0 + 1;
+    let overflow_frequency = NANOS_PER_SECOND as u64 / increment as u64;
+    let addend = (overflow_frequency << 32) / hclk.0 as u64;
+
+    (increment as u8, addend as u32)
+}
+
+/// Enables the timestamping, and starts the clock at zero
+///
+/// Returns the addend for the nominal frequency.
+pub(crate) fn enable(
+    ethernet_mac: &ETHERNET_MAC,
+    ethernet_ptp: &ETHERNET_PTP,
+    hclk: Hertz,
+) -> Result<u32, PtpError> {
+    let (increment, addend) = increment_and_addend(hclk);
+
+    // The target time interrupt is only unmasked on request
+    ethernet_mac.macimr.modify(|_, w| w.tstim().set_bit());
+
+    // Timestamp all frames, and roll the subseconds over at one second
+    ethernet_ptp
+        .ptptscr
+        .modify(|_, w| w.tse().set_bit().tsssr().set_bit().tssarfe().set_bit());
+
+    ethernet_ptp
+        .ptpssir
+        .write(|w| unsafe { w.stssi().bits(increment) });
+
+    ethernet_ptp
+        .ptptsar
+        .write(|w| unsafe { w.tsa().bits(addend) });
+    ethernet_ptp.ptptscr.modify(|_, w| w.ttsaru().set_bit());
+    // Wait until the addend has been taken over
+    wait_until_cleared(ethernet_ptp, |r| r.ttsaru().bit_is_set())?;
+
+    ethernet_ptp.ptptscr.modify(|_, w| w.tsfcu().set_bit());
+
+    let mut clock = PtpClock::new(ethernet_mac, ethernet_ptp, addend);
+    clock.set(Timestamp::new(0, 0))?;
+
+    Ok(addend)
+}
+
+/// Polls the timestamp control register, until the update flag has been cleared by the MAC
+fn wait_until_cleared(
+    ethernet_ptp: &ETHERNET_PTP,
+    is_set: impl Fn(&ptptscr::R) -> bool,
+) -> Result<(), PtpError> {
+    for _ in 0..PTP_TIMEOUT_POLLS {
+        if !is_set(&ethernet_ptp.ptptscr.read()) {
+            return Ok(());
+        }
+    }
+
+    Err(PtpError::Timeout)
+}
+
+/// The PTP clock, borrowed from the Ethernet device
+///
+/// Created with `EthernetDevice::ptp_clock`.
+pub struct PtpClock<'a> {
+    ethernet_mac: &'a ETHERNET_MAC,
+    ethernet_ptp: &'a ETHERNET_PTP,
+    nominal_addend: u32,
+}
+
+impl<'a> PtpClock<'a> {
+    pub(crate) fn new(
+        ethernet_mac: &'a ETHERNET_MAC,
+        ethernet_ptp: &'a ETHERNET_PTP,
+        nominal_addend: u32,
+    ) -> Self {
+        Self {
+            ethernet_mac,
+            ethernet_ptp,
+            nominal_addend,
+        }
+    }
+
+    /// Returns the current time of the clock
+    pub fn now(&self) -> Timestamp {
+        loop {
+            let high = self.ethernet_ptp.ptptshr.read().sts().bits();
+            let low = self.ethernet_ptp.ptptslr.read().bits();
+
+            // The seconds must not have changed while the nanoseconds were read
+            if self.ethernet_ptp.ptptshr.read().sts().bits() == high {
+                return Timestamp::from_registers(high, low);
+            }
+        }
+    }
+
+    /// Sets the clock to the given time
+    pub fn set(&mut self, time: Timestamp) -> Result<(), PtpError> {
+        self.write_update(time.seconds, time.nanoseconds, false);
+
+        self.ethernet_ptp.ptptscr.modify(|_, w| w.tssti().set_bit());
+        // Wait until the time has been initialised
+        wait_until_cleared(self.ethernet_ptp, |r| r.tssti().bit_is_set())
+    }
+
+    /// Steps the clock by the given offset in nanoseconds
+    pub fn adjust_coarse(&mut self, offset: i64) -> Result<(), PtpError> {
+        let magnitude = Timestamp::from_nanos(offset.unsigned_abs());
+
+        self.write_update(magnitude.seconds, magnitude.nanoseconds, offset < 0);
+
+        self.ethernet_ptp.ptptscr.modify(|_, w| w.tsstu().set_bit());
+        // Wait until the offset has been applied
+        wait_until_cleared(self.ethernet_ptp, |r| r.tsstu().bit_is_set())
+    }
+
+    /// Sets the frequency of the clock, relative to its nominal frequency, in parts per billion
+    ///
+    /// The nominal frequency is given by HCLK, so the adjustment compensates its deviation.
+    pub fn adjust_fine(&mut self, ppb: i32) -> Result<(), PtpError> {
+        let nominal = self.nominal_addend as i64;
+        let addend = nominal + nominal * ppb as i64 / NANOS_PER_SECOND as i64;
+        let addend = addend.clamp(0, u32::MAX as i64) as u32;
+
+        self.ethernet_ptp
+            .ptptsar
+            .write(|w| unsafe { w.tsa().bits(addend) });
+        self.ethernet_ptp
+            .ptptscr
+            .modify(|_, w| w.ttsaru().set_bit());
+        // Wait until the addend has been taken over
+        wait_until_cleared(self.ethernet_ptp, |r| r.ttsaru().bit_is_set())
+    }
+
+    /// Sets the time, at which the target time interrupt is triggered
+    ///
+    /// The interrupt is triggered once, the `ETH` interrupt handler must call
+    /// `ethernet::interrupt::on_interrupt`, which reports it.
+    pub fn set_target_time(&mut self, time: Timestamp) {
+        self.ethernet_ptp
+            .ptptthr
+            .write(|w| unsafe { w.ttsh().bits(time.seconds) });
+        self.ethernet_ptp
+            .ptpttlr
+            .write(|w| unsafe { w.ttsl().bits(time.nanoseconds) });
+
+        self.ethernet_ptp.ptptscr.modify(|_, w| w.tsite().set_bit());
+        self.ethernet_mac
+            .macimr
+            .modify(|_, w| w.tstim().clear_bit());
+    }
+
+    /// Disables a pending target time interrupt
+    pub fn cancel_target_time(&mut self) {
+        self.ethernet_mac.macimr.modify(|_, w| w.tstim().set_bit());
+        self.ethernet_ptp
+            .ptptscr
+            .modify(|_, w| w.tsite().clear_bit());
+    }
+
+    /// Returns true, if the target time has been reached since the last call
+    ///
+    /// This includes a target time, which has been reported by `ethernet::interrupt::on_interrupt`.
+    pub fn is_target_time_reached(&mut self) -> bool {
+        // Reading the status register clears the flag,
+        // so the interrupt handler latches it, when it reads the register first
+        let reached = self.ethernet_ptp.ptptssr.read().tsttr().bit_is_set();
+
+        interrupt::take_target_time_reached() || reached
+    }
+
+    fn write_update(&mut self, seconds: u32, nanoseconds: u32, subtract: bool) {
+        self.ethernet_ptp
+            .ptptshur
+            .write(|w| unsafe { w.tsus().bits(seconds) });
+        self.ethernet_ptp
+            .ptptslur
+            .write(|w| unsafe { w.tsupns().bit(subtract).tsuss().bits(nanoseconds) });
+    }
+}
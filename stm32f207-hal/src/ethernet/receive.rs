@@ -1,77 +1,83 @@
 use stm32f2::stm32f217::ETHERNET_DMA;
 
-use super::ring::{Receive, RingEntry};
+use super::{
+    descriptor::Descriptor,
+    errors::ReceiveFrameErrors,
+    ptp::Timestamp,
+    ring::{Receive, Ring, RingEntry},
+    vlan,
+};
 
 pub enum ReceiveError {
     BufferEmpty,
+    /// The frame did not fit into the ring, or is too long to be copied out of several entries
     FrameTruncated,
-    DMAError,
+    DMAError(ReceiveFrameErrors),
+    /// The frame is tagged, but its tag does not match the VLAN filter
+    VlanMismatch,
 }
 
-impl RingEntry<Receive> {
-    fn receive_frame(&mut self) -> Result<ReceiveFrame, ReceiveError> {
-        if self.is_owned() {
-            return Err(ReceiveError::BufferEmpty);
-        }
-
-        if unsafe { self.has_error() } {
-            unsafe { self.set_owned() };
-            return Err(ReceiveError::DMAError);
-        }
-
-        // Check if frame fit into the buffer
-        // If not, set self.is_owned()
-        let length = match unsafe { self.get_buffer1_len() } {
-            Some(x) => x,
-            // Frame did not fit into the buffer
-            None => {
-                unsafe { self.set_owned() };
-                return Err(ReceiveError::FrameTruncated);
-            }
-        };
-
-        Ok(ReceiveFrame {
-            entry: self,
-            length,
-        })
-    }
+/// The entries, which hold a frame
+#[derive(Clone, Copy)]
+struct FrameSpan {
+    // Index of the first entry
+    first: usize,
+    entries: usize,
+    length: usize,
 }
 
 pub struct ReceiveFrame<'a> {
-    entry: &'a mut RingEntry<Receive>,
-    length: usize,
+    bytes: &'a mut [u8],
+    // The descriptor of a frame in a single entry, which is handed back to the DMA on drop.
+    // Frames spanning several entries have already been copied.
+    descriptor: Option<&'a mut Descriptor<Receive>>,
+    timestamp: Option<Timestamp>,
 }
 
 impl<'a> ReceiveFrame<'a> {
+    pub(crate) fn as_bytes(&self) -> &[u8] {
+        self.bytes
+    }
+
     pub(crate) fn as_bytes_mut(&mut self) -> &mut [u8] {
-        // Safe, because a TransmitFrame only exists when:
-        // - It has been checked that the entry is not currently owned by the DMA Controller
-        // - As the frame holds a unique engine to the RingEntry, the buffer cannot be used elsewhere
-        let bytes = unsafe { self.entry.bytes_unchecked_mut() };
+        self.bytes
+    }
 
-        &mut bytes[..self.length]
+    /// Returns the time, at which the frame has been received, if it has been timestamped
+    pub(crate) fn timestamp(&self) -> Option<Timestamp> {
+        self.timestamp
     }
 }
 
 impl<'a> Drop for ReceiveFrame<'a> {
     fn drop(&mut self) {
-        unsafe { self.entry.set_owned() }
+        if let Some(descriptor) = self.descriptor.as_mut() {
+            unsafe { descriptor.set_owned() }
+        }
     }
 }
 
-pub struct ReceiveRing<'a> {
-    entries: &'a mut [RingEntry<Receive>],
+pub struct ReceiveRing<'a, const N: usize> {
+    entries: &'a mut [RingEntry<Receive, N>],
     // Index of the entry where the next frame should be placed
     next_entry: usize,
+    last_timestamp: Option<Timestamp>,
+    // Frames, which span several entries, are copied here, to be contiguous
+    frame_buffer: &'a mut [u8],
+    // Tagged frames, which do not match the VLAN tag register, are dropped
+    vlan_filtering: bool,
 }
 
-impl<'a> ReceiveRing<'a> {
-    pub fn new(entries: &'a mut [RingEntry<Receive>], ethernet_dma: &ETHERNET_DMA) -> Self {
-        assert!(!entries.is_empty());
+impl<'a, const N: usize> ReceiveRing<'a, N> {
+    pub fn new(ring: Ring<'a, Receive, N>, ethernet_dma: &ETHERNET_DMA) -> Self {
+        assert!(!ring.entries.is_empty());
 
         let mut result = Self {
-            entries,
+            entries: ring.entries,
             next_entry: 0,
+            last_timestamp: None,
+            frame_buffer: ring.frame_buffer,
+            vlan_filtering: false,
         };
 
         result.init_ring_entry_buffers();
@@ -103,7 +109,7 @@ impl<'a> ReceiveRing<'a> {
 
     fn chain_entry_descriptors(&mut self) {
         let first = self.entries[0].descriptor_ptr();
-        let mut previous_entry: Option<&mut RingEntry<Receive>> = None;
+        let mut previous_entry: Option<&mut RingEntry<Receive, N>> = None;
         for entry in self.entries.iter_mut() {
             if let Some(previous_entry) = previous_entry {
                 unsafe { previous_entry.set_next_descriptor(entry.descriptor_ptr()) }
@@ -120,23 +126,154 @@ impl<'a> ReceiveRing<'a> {
         }
     }
 
-    pub fn receive_frame(
-        &mut self,
-        ethernet_dma: &ETHERNET_DMA,
-    ) -> Result<ReceiveFrame, ReceiveError> {
-        self.request_poll(ethernet_dma);
+    /// Skips the unusable entries, and returns the length of the next frame
+    ///
+    /// Every skipped frame is reported once, so that the caller can count it.
+    pub fn poll_frame(&mut self) -> Result<usize, ReceiveError> {
+        Ok(self.find_frame()?.length)
+    }
+
+    fn find_frame(&mut self) -> Result<FrameSpan, ReceiveError> {
+        loop {
+            let first = &self.entries[self.next_entry];
+
+            if first.is_owned() {
+                return Err(ReceiveError::BufferEmpty);
+            }
+
+            // The rest of a frame, whose start has already been skipped
+            if unsafe { !first.is_first_segment() } {
+                self.release_entries(1);
+                continue;
+            }
+
+            let span = self.find_last_segment()?;
+            let last = &self.entries[(span.first + span.entries - 1) % self.entries.len()];
+
+            if let Some(errors) = unsafe { last.errors() } {
+                self.release_entries(span.entries);
+                return Err(ReceiveError::DMAError(errors));
+            }
+
+            if span.entries > 1 && span.length > self.frame_buffer.len() {
+                self.release_entries(span.entries);
+                return Err(ReceiveError::FrameTruncated);
+            }
+
+            if self.vlan_filtering && !self.matches_vlan_filter(span) {
+                self.release_entries(span.entries);
+                return Err(ReceiveError::VlanMismatch);
+            }
+
+            return Ok(span);
+        }
+    }
+
+    /// Returns false, if the frame is tagged, but the MAC did not match its tag
+    ///
+    /// Only the tags of frames, whose header is in the first entry, are checked.
+    fn matches_vlan_filter(&mut self, span: FrameSpan) -> bool {
+        let last = &self.entries[(span.first + span.entries - 1) % self.entries.len()];
 
+        if unsafe { last.matches_vlan_tag() } {
+            return true;
+        }
+
+        let bytes = unsafe { self.entries[span.first].bytes_unchecked_mut() };
+
+        vlan::tag(&bytes[..usize::min(bytes.len(), span.length)]).is_none()
+    }
+
+    /// Selects, whether tagged frames, which do not match the VLAN tag register, are dropped
+    pub fn set_vlan_filtering(&mut self, enabled: bool) {
+        self.vlan_filtering = enabled;
+    }
+
+    /// Returns the entries of the frame, which starts at the next entry
+    fn find_last_segment(&mut self) -> Result<FrameSpan, ReceiveError> {
+        let entries_len = self.entries.len();
+
+        for entries in 1..=entries_len {
+            let entry = &self.entries[(self.next_entry + entries - 1) % entries_len];
+
+            // The DMA is still writing the frame
+            if entry.is_owned() {
+                return Err(ReceiveError::BufferEmpty);
+            }
+
+            if let Some(length) = unsafe { entry.get_frame_len() } {
+                return Ok(FrameSpan {
+                    first: self.next_entry,
+                    entries,
+                    length,
+                });
+            }
+        }
+
+        // The frame fills the whole ring, so it can not be completed
+        self.release_entries(entries_len);
+        Err(ReceiveError::FrameTruncated)
+    }
+
+    pub fn receive_frame(&mut self) -> Result<ReceiveFrame<'_>, ReceiveError> {
+        let span = self.find_frame()?;
         let entries_len = self.entries.len();
-        let frame = self.entries[self.next_entry].receive_frame()?;
 
-        // increment next entry
+        let last = &self.entries[(span.first + span.entries - 1) % entries_len];
+        self.last_timestamp = unsafe { last.timestamp() };
+
+        if span.entries == 1 {
+            self.increment_next_entry();
+
+            // Safe, because the entry is not owned by the DMA Controller,
+            // and the frame holds a unique reference to it until it is handed back
+            let (descriptor, bytes) = unsafe { self.entries[span.first].split_unchecked_mut() };
+
+            return Ok(ReceiveFrame {
+                bytes: &mut bytes[..span.length],
+                descriptor: Some(descriptor),
+                timestamp: self.last_timestamp,
+            });
+        }
+
+        // Copy the segments, all but the last one fill their buffer
+        for segment in 0..span.entries {
+            let entry = &mut self.entries[(span.first + segment) % entries_len];
+            let start = usize::min(segment * N, span.length);
+            let end = usize::min(start + N, span.length);
+
+            let bytes = unsafe { entry.bytes_unchecked_mut() };
+            self.frame_buffer[start..end].copy_from_slice(&bytes[..end - start]);
+        }
+
+        self.release_entries(span.entries);
+
+        Ok(ReceiveFrame {
+            bytes: &mut self.frame_buffer[..span.length],
+            descriptor: None,
+            timestamp: self.last_timestamp,
+        })
+    }
+
+    /// Hands the next entries back to the DMA
+    fn release_entries(&mut self, entries: usize) {
+        for _ in 0..entries {
+            unsafe { self.entries[self.next_entry].set_owned() };
+            self.increment_next_entry();
+        }
+    }
+
+    fn increment_next_entry(&mut self) {
         self.next_entry += 1;
 
-        if self.next_entry >= entries_len {
+        if self.next_entry >= self.entries.len() {
             self.next_entry = 0;
         }
+    }
 
-        Ok(frame)
+    /// Returns the timestamp of the last received frame
+    pub fn last_timestamp(&self) -> Option<Timestamp> {
+        self.last_timestamp
     }
 
     pub fn request_poll(&mut self, ethernet_dma: &ETHERNET_DMA) {
@@ -4,25 +4,57 @@ use vcell::VolatileCell;
 
 use super::{
     descriptor::{Descriptor, SetBufferError},
+    errors::{ReceiveFrameErrors, TransmitFrameErrors},
+    ptp::Timestamp,
     transmit::TransmitError,
     MAX_TRANSMISSION_UNIT,
 };
 
+/// The largest buffer of a `RingEntry`, which fits into the 13 bit length field of its descriptor
+pub const MAX_BUFFER_SIZE: usize = 0x1FFE;
+
+/// The size of the buffers, which hold a whole frame of `MAX_TRANSMISSION_UNIT` bytes,
+/// rounded up to a multiple of 4 bytes as required for receive buffers
+pub const DEFAULT_BUFFER_SIZE: usize = MAX_TRANSMISSION_UNIT.next_multiple_of(4);
+
 #[derive(Clone, Copy, Default, Debug, PartialEq, Eq)]
 pub struct Receive;
 #[derive(Clone, Copy, Default, Debug, PartialEq, Eq)]
 pub struct Transmit;
 
+/// A descriptor of the Ethernet DMA, and the buffer it points to
+///
+/// `N` is the size of the buffer, at most `MAX_BUFFER_SIZE`, and a multiple of 4 for receive entries.
+/// Frames, which are longer than a single buffer, span several entries,
+/// so e.g. 32 entries with 512 bytes may be used instead of 16 full frames.
+/// The entries of a ring must be able to hold at least one frame of `MAX_TRANSMISSION_UNIT` bytes,
+/// and such frames are copied through the frame buffer of the [`Ring`].
 #[repr(C)]
-pub struct RingEntry<T> {
+pub struct RingEntry<T, const N: usize = DEFAULT_BUFFER_SIZE> {
     // An inner type wrapped in UnsafeCell is used here,
     // because the InnerRingEntry may always be aliased and accessed by the DMA Controller.
     // Therefore, the Ownership rules may be violated
     descriptor: Descriptor<T>,
-    buffer: VolatileCell<[u8; MAX_TRANSMISSION_UNIT]>,
+    buffer: VolatileCell<[u8; N]>,
 }
 
-impl<T> RingEntry<T> {
+impl<T, const N: usize> RingEntry<T, N> {
+    // Evaluated when an entry is created, so that a wrong size fails to compile
+    const BUFFER_SIZE_CHECK: () = assert!(
+        N > 0 && N <= MAX_BUFFER_SIZE,
+        "The buffer of a RingEntry must hold between 1 and 8190 bytes"
+    );
+
+    /// The length of the frame buffer, which a ring of these entries requires
+    ///
+    /// Frames only span several entries, if the buffers are shorter than `MAX_TRANSMISSION_UNIT`,
+    /// otherwise no frame buffer is needed.
+    pub const FRAME_BUFFER_LEN: usize = if N < MAX_TRANSMISSION_UNIT {
+        MAX_TRANSMISSION_UNIT
+    } else {
+        0
+    };
+
     /// MUST be called once, after the RingEntry was moved into position
     pub(crate) fn init(&mut self) {
         assert!(!self.is_owned());
@@ -41,10 +73,6 @@ impl<T> RingEntry<T> {
         self.descriptor.is_owned()
     }
 
-    pub(crate) unsafe fn has_error(&self) -> bool {
-        self.descriptor.has_error()
-    }
-
     /// Safety: descriptor must be configured properly - init must have been called
     pub(crate) unsafe fn set_owned(&mut self) {
         self.descriptor.set_owned();
@@ -63,6 +91,18 @@ impl<T> RingEntry<T> {
         result
     }
 
+    /// Returns the descriptor and the buffer, e.g. to give the descriptor back to the DMA,
+    /// once the bytes are no longer used
+    pub(crate) unsafe fn split_unchecked_mut(&mut self) -> (&mut Descriptor<T>, &mut [u8]) {
+        compiler_fence(Ordering::SeqCst);
+
+        let bytes = &mut *self.buffer.as_ptr();
+
+        compiler_fence(Ordering::SeqCst);
+
+        (&mut self.descriptor, bytes)
+    }
+
     pub(crate) fn descriptor_ptr(&self) -> *const Descriptor<T> {
         &self.descriptor
     }
@@ -72,14 +112,33 @@ impl<T> RingEntry<T> {
     }
 }
 
-impl RingEntry<Transmit> {
+impl<const N: usize> RingEntry<Transmit, N> {
+    // NOTE(allow) The constant is only used to initialise the array elements
+    #[allow(clippy::declare_interior_mutable_const)]
+    const ENTRY: Self = Self::new_transmit();
+
     pub const fn new_transmit() -> Self {
+        let () = Self::BUFFER_SIZE_CHECK;
+
         Self {
             descriptor: Descriptor::new_transmit(),
-            buffer: VolatileCell::new([0; MAX_TRANSMISSION_UNIT]),
+            buffer: VolatileCell::new([0; N]),
         }
     }
 
+    /// Creates the entries of a transmit ring, e.g. to initialise a static array
+    pub const fn new_ring<const LEN: usize>() -> [Self; LEN] {
+        [Self::ENTRY; LEN]
+    }
+
+    /// Resets the descriptor to its initial state, which is not owned by the DMA
+    ///
+    /// Used for entries in memory, which is not initialised at startup.
+    #[doc(hidden)]
+    pub fn reset_descriptor(&mut self) {
+        self.descriptor = Descriptor::new_transmit();
+    }
+
     pub(crate) unsafe fn set_buffer1_len(&mut self, len: usize) -> Result<(), TransmitError> {
         self.descriptor
             .set_buffer1_len(len)
@@ -88,20 +147,90 @@ impl RingEntry<Transmit> {
             })
     }
 
+    /// Returns the timestamp of the transmitted frame, once the DMA has released the entry
+    pub(crate) fn timestamp(&self) -> Option<Timestamp> {
+        if self.is_owned() {
+            return None;
+        }
+
+        // Safe: Entry is not accessed by the dma engine
+        unsafe { self.descriptor.timestamp() }
+    }
+
+    /// Returns the errors of the previous frame in this entry, once the DMA has released it
+    pub(crate) fn take_errors(&mut self) -> Option<TransmitFrameErrors> {
+        if self.is_owned() {
+            return None;
+        }
+
+        // Safe: Entry is not accessed by the dma engine
+        unsafe { self.descriptor.take_errors() }
+    }
+
+    /// Marks the entry as the first and/or last segment of the frame
+    pub(crate) unsafe fn set_segments(&mut self, first: bool, last: bool) {
+        self.descriptor.set_segments(first, last)
+    }
+
     pub(crate) unsafe fn set_transmit_end_of_ring(&mut self) {
         self.descriptor.set_transmit_end_of_ring()
     }
 }
 
-impl RingEntry<Receive> {
+impl<const N: usize> RingEntry<Receive, N> {
+    // NOTE(allow) The constant is only used to initialise the array elements
+    #[allow(clippy::declare_interior_mutable_const)]
+    const ENTRY: Self = Self::new_receive();
+
+    // The DMA writes received frames in words, so the buffer size must be a multiple of 4
+    // NOTE(allow) `is_multiple_of` would require Rust 1.87
+    #[allow(clippy::manual_is_multiple_of)]
+    const RECEIVE_BUFFER_SIZE_CHECK: () = assert!(
+        N % 4 == 0,
+        "The buffer of a receive RingEntry must be a multiple of 4 bytes"
+    );
+
     pub const fn new_receive() -> Self {
+        let () = Self::BUFFER_SIZE_CHECK;
+        let () = Self::RECEIVE_BUFFER_SIZE_CHECK;
+
         Self {
             descriptor: Descriptor::new_receive(),
-            buffer: VolatileCell::new([0; MAX_TRANSMISSION_UNIT]),
+            buffer: VolatileCell::new([0; N]),
         }
     }
 
-    pub(crate) unsafe fn get_buffer1_len(&mut self) -> Option<usize> {
+    /// Creates the entries of a receive ring, e.g. to initialise a static array
+    pub const fn new_ring<const LEN: usize>() -> [Self; LEN] {
+        [Self::ENTRY; LEN]
+    }
+
+    /// Resets the descriptor to its initial state, which is not owned by the DMA
+    ///
+    /// Used for entries in memory, which is not initialised at startup.
+    #[doc(hidden)]
+    pub fn reset_descriptor(&mut self) {
+        self.descriptor = Descriptor::new_receive();
+    }
+
+    pub(crate) unsafe fn errors(&self) -> Option<ReceiveFrameErrors> {
+        self.descriptor.errors()
+    }
+
+    pub(crate) unsafe fn is_first_segment(&self) -> bool {
+        self.descriptor.is_first_segment()
+    }
+
+    pub(crate) unsafe fn matches_vlan_tag(&self) -> bool {
+        self.descriptor.matches_vlan_tag()
+    }
+
+    pub(crate) unsafe fn timestamp(&self) -> Option<Timestamp> {
+        self.descriptor.timestamp()
+    }
+
+    /// Returns the length of the whole frame, if the entry holds its last segment
+    pub(crate) unsafe fn get_frame_len(&self) -> Option<usize> {
         self.descriptor.get_frame_len()
     }
 
@@ -109,3 +238,79 @@ impl RingEntry<Receive> {
         self.descriptor.set_receive_end_of_ring()
     }
 }
+
+/// The entries of a ring, and the buffer, which frames spanning several entries are copied through
+///
+/// The frame buffer must hold `RingEntry::FRAME_BUFFER_LEN` bytes,
+/// longer frames, which span several entries, are dropped.
+pub struct Ring<'a, T, const N: usize = DEFAULT_BUFFER_SIZE> {
+    pub(crate) entries: &'a mut [RingEntry<T, N>],
+    pub(crate) frame_buffer: &'a mut [u8],
+}
+
+impl<'a, T, const N: usize> Ring<'a, T, N> {
+    pub fn new(entries: &'a mut [RingEntry<T, N>], frame_buffer: &'a mut [u8]) -> Self {
+        Self {
+            entries,
+            frame_buffer,
+        }
+    }
+}
+
+/// Declares a static `Ring` of `RingEntry`s and its frame buffer, and returns it
+///
+/// The first argument is the kind of the ring, `Receive` or `Transmit`,
+/// optionally followed by the size of the buffer of each entry, the second one its number of entries.
+/// Optionally, the linker section of the ring can be given as third argument,
+/// e.g. to place it into a memory region, which is accessible by the Ethernet DMA.
+/// The frame buffer is only accessed by the CPU, and it is empty, if the buffers hold a whole frame.
+///
+/// Like `cortex_m::singleton!`, each invocation returns `Some` only the first time it is executed,
+/// and `None` afterwards.
+///
+/// ```ignore
+/// let receive_ring = ethernet_ring!(Receive, 16).unwrap();
+/// let transmit_ring = ethernet_ring!(Transmit, 8, ".sram2").unwrap();
+/// let small_receive_ring = ethernet_ring!(Receive<512>, 32).unwrap();
+/// ```
+#[macro_export]
+macro_rules! ethernet_ring {
+    ($kind:ident $(<$size:tt>)?, $len:expr) => {
+        $crate::ethernet_ring!(@ring $kind [$($size)?], $len, [])
+    };
+    ($kind:ident $(<$size:tt>)?, $len:expr, $section:literal) => {
+        $crate::ethernet_ring!(@ring $kind [$($size)?], $len, [#[link_section = $section]])
+    };
+    (@ring $kind:ident [$($size:tt)?], $len:expr, [$(#[$attribute:meta])*]) => {{
+        use $crate::ethernet::ring::{$kind, Ring, RingEntry};
+
+        type Entry = RingEntry<$kind $(, $size)?>;
+
+        static TAKEN: ::core::sync::atomic::AtomicBool =
+            ::core::sync::atomic::AtomicBool::new(false);
+
+        $(#[$attribute])*
+        static mut RING: [Entry; $len] = Entry::new_ring::<$len>();
+
+        static mut FRAME_BUFFER: [u8; Entry::FRAME_BUFFER_LEN] = [0; Entry::FRAME_BUFFER_LEN];
+
+        if TAKEN.swap(true, ::core::sync::atomic::Ordering::AcqRel) {
+            None
+        } else {
+            // Safety: The flag guarantees, that the references are only handed out once
+            let (ring, frame_buffer): (&'static mut [Entry], &'static mut [u8]) = unsafe {
+                (
+                    &mut *::core::ptr::addr_of_mut!(RING),
+                    &mut *::core::ptr::addr_of_mut!(FRAME_BUFFER),
+                )
+            };
+
+            // The section might not be initialised at startup
+            for entry in ring.iter_mut() {
+                entry.reset_descriptor();
+            }
+
+            Some(Ring::new(ring, frame_buffer))
+        }
+    }};
+}
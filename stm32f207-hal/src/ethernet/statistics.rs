@@ -0,0 +1,104 @@
+//! Statistics of the MAC, counted by the management counters (MMC)
+//!
+//! The counters run independently of the `EthernetDevice`, which only configures the MAC,
+//! so they are accessed through their own peripheral.
+//! The MMC of the STM32F2 does not count multicast frames, and only counts
+//! received frames with CRC or alignment errors as bad frames.
+
+use stm32f2::stm32f217::ETHERNET_MMC;
+
+/// A snapshot of the management counters
+///
+/// All counters are 32 bit wide, and roll over to zero.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct EthernetStatistics {
+    /// Frames, which have been transmitted without errors
+    pub transmitted_good_frames: u32,
+    /// Frames, which have been transmitted after a single collision in half duplex mode
+    pub single_collisions: u32,
+    /// Frames, which have been transmitted after more than one collision in half duplex mode
+    pub multiple_collisions: u32,
+    /// Unicast frames, which have been received without errors
+    pub received_good_unicast_frames: u32,
+    /// Received frames with a CRC error
+    pub crc_errors: u32,
+    /// Received frames with an alignment error, i.e. an odd number of nibbles
+    pub alignment_errors: u32,
+}
+
+impl EthernetStatistics {
+    /// Returns the number of received frames, which have been dropped because of an error
+    pub fn received_bad_frames(&self) -> u32 {
+        self.crc_errors.wrapping_add(self.alignment_errors)
+    }
+}
+
+/// The management counters
+pub struct StatisticsCounters {
+    ethernet_mmc: ETHERNET_MMC,
+}
+
+impl StatisticsCounters {
+    /// Takes the counters, and resets them to zero
+    ///
+    /// The interrupts of the counters are masked, so that they do not trigger the `ETH`
+    /// interrupt when they reach half of their range.
+    pub fn new(ethernet_mmc: ETHERNET_MMC) -> Self {
+        ethernet_mmc
+            .mmcrimr
+            .write(|w| w.rfcem().set_bit().rfaem().set_bit().rgufm().set_bit());
+        ethernet_mmc
+            .mmctimr
+            .write(|w| w.tgfscm().set_bit().tgfmscm().set_bit().tgfm().set_bit());
+
+        let mut result = Self { ethernet_mmc };
+        result.reset();
+
+        result
+    }
+
+    /// Reads all counters
+    ///
+    /// With reset on read, the counters restart at zero afterwards.
+    pub fn read(&mut self) -> EthernetStatistics {
+        let mmc = &self.ethernet_mmc;
+
+        EthernetStatistics {
+            transmitted_good_frames: mmc.mmctgfcr.read().tgfc().bits(),
+            single_collisions: mmc.mmctgfsccr.read().tgfscc().bits(),
+            multiple_collisions: mmc.mmctgfmsccr.read().tgfmscc().bits(),
+            received_good_unicast_frames: mmc.mmcrgufcr.read().rgufc().bits(),
+            crc_errors: mmc.mmcrfcecr.read().rfcfc().bits(),
+            alignment_errors: mmc.mmcrfaecr.read().rfaec().bits(),
+        }
+    }
+
+    /// Resets all counters to zero
+    pub fn reset(&mut self) {
+        self.ethernet_mmc.mmccr.modify(|_, w| w.cr().set_bit());
+    }
+
+    /// Selects, whether every counter is reset to zero when it is read
+    ///
+    /// This way, every snapshot holds the frames since the previous one.
+    pub fn set_reset_on_read(&mut self, reset_on_read: bool) {
+        self.ethernet_mmc
+            .mmccr
+            .modify(|_, w| w.ror().bit(reset_on_read));
+    }
+
+    /// Stops all counters at their current values, so that they can be read consistently
+    pub fn freeze(&mut self) {
+        self.ethernet_mmc.mmccr.modify(|_, w| w.mcf().set_bit());
+    }
+
+    /// Lets the counters continue after `freeze`
+    pub fn unfreeze(&mut self) {
+        self.ethernet_mmc.mmccr.modify(|_, w| w.mcf().clear_bit());
+    }
+
+    /// Releases the peripheral
+    pub fn release(self) -> ETHERNET_MMC {
+        self.ethernet_mmc
+    }
+}
@@ -1,65 +1,53 @@
 use stm32f2::stm32f217::ETHERNET_DMA;
 
-use super::ring::{RingEntry, Transmit};
+use super::{
+    errors::ErrorCounters,
+    ptp::Timestamp,
+    ring::{Ring, RingEntry, Transmit},
+    vlan,
+};
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum TransmitError {
+    /// All entries are owned by the DMA, until their frames have been sent
     BufferFull,
+    /// The frame does not fit into the ring
     FrameTooLong,
+    /// The frame is shorter than the Ethernet header
+    FrameTooShort,
 }
 
-impl RingEntry<Transmit> {
-    fn prepare_frame(&mut self, length: usize) -> Result<TransmitFrame, TransmitError> {
-        if self.is_owned() {
-            return Err(TransmitError::BufferFull);
-        }
-
-        // Safe: Entry is not accessed by the dma engine
-        unsafe {
-            self.set_buffer1_len(length)?;
-        }
-
-        Ok(TransmitFrame {
-            entry: self,
-            length,
-        })
-    }
-}
-
-pub struct TransmitFrame<'a> {
-    entry: &'a mut RingEntry<Transmit>,
-    length: usize,
-}
-
-impl<'a> TransmitFrame<'a> {
-    pub(crate) fn as_bytes_mut(&mut self) -> &mut [u8] {
-        // Safe, because a TransmitFrame only exists when:
-        // - It has been checked that the entry is not currently owned by the DMA Controller
-        // - As the frame holds a unique engine to the RingEntry, the buffer cannot be used elsewhere
-        let bytes = unsafe { self.entry.bytes_unchecked_mut() };
-
-        &mut bytes[..self.length]
-    }
-
-    fn send(self) {
-        unsafe {
-            self.entry.set_owned();
-        }
-    }
+/// Identifies a transmitted frame, to read its timestamp
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TransmitHandle {
+    // Index of the last entry of the frame, which holds its timestamp
+    last_entry: usize,
+    // Number of entries used by the ring, including the ones of the frame
+    position: u32,
 }
 
-pub struct TransmitRing<'a> {
-    entries: &'a mut [RingEntry<Transmit>],
+pub struct TransmitRing<'a, const N: usize> {
+    entries: &'a mut [RingEntry<Transmit, N>],
     // Index of the entry where the next frame should be placed
     next_entry: usize,
+    // The last transmitted frame
+    last_frame: Option<TransmitHandle>,
+    // Number of entries used for frames, to tell when an entry has been reused
+    entries_used: u32,
+    // Frames, which span several entries, are written here, and then copied into the entries
+    frame_buffer: &'a mut [u8],
 }
 
-impl<'a> TransmitRing<'a> {
-    pub fn new(entries: &'a mut [RingEntry<Transmit>], ethernet_dma: &ETHERNET_DMA) -> Self {
-        assert!(!entries.is_empty());
+impl<'a, const N: usize> TransmitRing<'a, N> {
+    pub fn new(ring: Ring<'a, Transmit, N>, ethernet_dma: &ETHERNET_DMA) -> Self {
+        assert!(!ring.entries.is_empty());
 
         let mut result = Self {
-            entries,
+            entries: ring.entries,
             next_entry: 0,
+            last_frame: None,
+            entries_used: 0,
+            frame_buffer: ring.frame_buffer,
         };
 
         result.init_ring_entry_buffers();
@@ -84,7 +72,7 @@ impl<'a> TransmitRing<'a> {
 
     fn chain_entry_descriptors(&mut self) {
         let first = self.entries[0].descriptor_ptr();
-        let mut previous_entry: Option<&mut RingEntry<Transmit>> = None;
+        let mut previous_entry: Option<&mut RingEntry<Transmit, N>> = None;
         for entry in self.entries.iter_mut() {
             if let Some(previous_entry) = previous_entry {
                 unsafe { previous_entry.set_next_descriptor(entry.descriptor_ptr()) }
@@ -106,23 +94,199 @@ impl<'a> TransmitRing<'a> {
         length: usize,
         func: F,
         ethernet_dma: &ETHERNET_DMA,
-    ) -> Result<R, TransmitError>
+        error_counters: &mut ErrorCounters,
+    ) -> Result<(R, TransmitHandle), TransmitError>
+    where
+        F: FnOnce(&mut [u8]) -> R,
+    {
+        let entries = self.prepare_entries(length, error_counters)?;
+
+        Ok(self.send_frame(length, entries, func, ethernet_dma))
+    }
+
+    /// Transmits the frame, or drops and counts it, if it can not be transmitted
+    ///
+    /// The closure is called in both cases, with a buffer of the length.
+    ///
+    /// # Panics
+    ///
+    /// If a frame, which can not be transmitted, is longer than both the frame buffer and an entry,
+    /// because no buffer could hold it.
+    #[cfg(feature = "embassy-net")]
+    pub fn transmit_or_drop<F, R>(
+        &mut self,
+        length: usize,
+        func: F,
+        ethernet_dma: &ETHERNET_DMA,
+        error_counters: &mut ErrorCounters,
+    ) -> R
+    where
+        F: FnOnce(&mut [u8]) -> R,
+    {
+        if let Ok(entries) = self.prepare_entries(length, error_counters) {
+            return self.send_frame(length, entries, func, ethernet_dma).0;
+        }
+
+        error_counters.count_dropped_transmit_frame();
+
+        if length <= self.frame_buffer.len() {
+            return func(&mut self.frame_buffer[..length]);
+        }
+
+        let entry = &mut self.entries[self.next_entry];
+        assert!(
+            length <= N && !entry.is_owned(),
+            "No buffer can hold the frame, which is dropped"
+        );
+
+        // Safe: Entry is not accessed by the dma engine
+        func(unsafe { &mut entry.bytes_unchecked_mut()[..length] })
+    }
+
+    /// Checks, that a frame of the length can be transmitted, and sets up the descriptors of its entries
+    ///
+    /// Returns the number of entries, which are only handed to the DMA by `send_frame`.
+    fn prepare_entries(
+        &mut self,
+        length: usize,
+        error_counters: &mut ErrorCounters,
+    ) -> Result<usize, TransmitError> {
+        if length < vlan::HEADER_LENGTH {
+            return Err(TransmitError::FrameTooShort);
+        }
+
+        if !self.fits(length) {
+            return Err(TransmitError::FrameTooLong);
+        }
+
+        let entries = Self::entries_for(length);
+
+        for segment in 0..entries {
+            let entry = self.entry_mut(segment);
+
+            if entry.is_owned() {
+                return Err(TransmitError::BufferFull);
+            }
+
+            // The errors of the previous frame in the entry are counted, before it is reused
+            if let Some(errors) = entry.take_errors() {
+                error_counters.count_transmit_errors(errors);
+            }
+        }
+
+        // All but the last segment fill their buffer
+        for segment in 0..entries {
+            let start = segment * N;
+            let end = usize::min(start + N, length);
+            let entry = self.entry_mut(segment);
+
+            // Safe: Entry is not accessed by the dma engine
+            unsafe {
+                entry.set_buffer1_len(end - start)?;
+                entry.set_segments(segment == 0, segment == entries - 1);
+            }
+        }
+
+        Ok(entries)
+    }
+
+    /// Lets the closure fill the prepared entries, and hands them to the DMA
+    fn send_frame<F, R>(
+        &mut self,
+        length: usize,
+        entries: usize,
+        func: F,
+        ethernet_dma: &ETHERNET_DMA,
+    ) -> (R, TransmitHandle)
     where
         F: FnOnce(&mut [u8]) -> R,
     {
-        // Prepare the buffer
-        let mut frame: TransmitFrame = self.entries[self.next_entry].prepare_frame(length)?;
+        let result = if entries == 1 {
+            let entry = &mut self.entries[self.next_entry];
+
+            // Safe, because the entry has been checked to not be owned by the DMA,
+            // and it is only handed over, once the closure has returned
+            let result = func(unsafe { &mut entry.bytes_unchecked_mut()[..length] });
+
+            unsafe { entry.set_owned() };
+
+            result
+        } else {
+            let result = func(&mut self.frame_buffer[..length]);
+
+            self.send_segments(length, entries);
 
-        // Callback fills the buffer
-        let result = func(frame.as_bytes_mut());
+            result
+        };
 
-        frame.send();
+        self.entries_used = self.entries_used.wrapping_add(entries as u32);
+        let handle = TransmitHandle {
+            last_entry: (self.next_entry + entries - 1) % self.entries.len(),
+            position: self.entries_used,
+        };
+        self.last_frame = Some(handle);
 
-        self.increment_next_entry();
+        for _ in 0..entries {
+            self.increment_next_entry();
+        }
 
         self.request_poll(ethernet_dma);
 
-        Ok(result)
+        (result, handle)
+    }
+
+    /// Returns true, if a frame of the length fits into the ring
+    ///
+    /// Frames, which span several entries, must also fit into the frame buffer.
+    pub fn fits(&self, length: usize) -> bool {
+        let entries = Self::entries_for(length);
+
+        entries <= self.entries.len() && (entries == 1 || length <= self.frame_buffer.len())
+    }
+
+    /// Returns true, if the entries for a frame of the length are not owned by the DMA
+    #[cfg(feature = "embassy-net")]
+    pub fn can_transmit(&self, length: usize) -> bool {
+        let entries = Self::entries_for(length);
+        let entries_len = self.entries.len();
+
+        self.fits(length)
+            && (0..entries)
+                .all(|segment| !self.entries[(self.next_entry + segment) % entries_len].is_owned())
+    }
+
+    /// Returns the number of entries, which a frame of the length spans
+    fn entries_for(length: usize) -> usize {
+        usize::max(1, length.div_ceil(N))
+    }
+
+    /// Copies the frame from the frame buffer into the prepared entries, and hands them to the DMA
+    fn send_segments(&mut self, length: usize, entries: usize) {
+        let entries_len = self.entries.len();
+
+        for segment in 0..entries {
+            let start = segment * N;
+            let end = usize::min(start + N, length);
+            let entry = &mut self.entries[(self.next_entry + segment) % entries_len];
+
+            // Safe: Entry is not accessed by the dma engine
+            unsafe {
+                entry.bytes_unchecked_mut()[..end - start]
+                    .copy_from_slice(&self.frame_buffer[start..end]);
+            }
+        }
+
+        // The first entry is handed over last, so that the DMA does not start on an incomplete frame
+        for segment in (0..entries).rev() {
+            unsafe { self.entry_mut(segment).set_owned() };
+        }
+    }
+
+    /// Returns the entry, which follows the next entry by the offset
+    fn entry_mut(&mut self, offset: usize) -> &mut RingEntry<Transmit, N> {
+        let index = (self.next_entry + offset) % self.entries.len();
+
+        &mut self.entries[index]
     }
 
     fn increment_next_entry(&mut self) {
@@ -133,6 +297,22 @@ impl<'a> TransmitRing<'a> {
         }
     }
 
+    /// Returns the timestamp of the last transmitted frame, once it has been sent
+    pub fn last_timestamp(&self) -> Option<Timestamp> {
+        self.timestamp(self.last_frame?)
+    }
+
+    /// Returns the timestamp of the frame, once it has been sent
+    ///
+    /// Returns None, if its entry has already been reused for another frame.
+    pub fn timestamp(&self, handle: TransmitHandle) -> Option<Timestamp> {
+        if self.entries_used.wrapping_sub(handle.position) as usize >= self.entries.len() {
+            return None;
+        }
+
+        self.entries[handle.last_entry].timestamp()
+    }
+
     pub fn request_poll(&mut self, ethernet_dma: &ETHERNET_DMA) {
         ethernet_dma.dmatpdr.write(|w| unsafe { w.tpd().bits(1) })
     }
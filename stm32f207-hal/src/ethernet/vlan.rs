@@ -0,0 +1,92 @@
+//! IEEE 802.1Q VLAN tags of raw frames
+//!
+//! The tag is placed between the source address and the EtherType of the frame.
+//! It consists of the VLAN EtherType and the tag control information (TCI).
+
+use super::VLAN_TAG_LENGTH;
+
+/// The EtherType, which marks a tagged frame
+pub const VLAN_ETHER_TYPE: u16 = 0x8100;
+
+/// Length of the untagged Ethernet header, the addresses and the EtherType
+pub const HEADER_LENGTH: usize = 14;
+
+// The tag follows the destination and the source address
+const TAG_OFFSET: usize = 12;
+
+/// The tag control information of a VLAN tag
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct VlanTag {
+    /// Priority code point, only the lower 3 bits are used
+    pub priority: u8,
+    /// Drop eligible indicator
+    pub drop_eligible: bool,
+    /// VLAN identifier, only the lower 12 bits are used
+    pub identifier: u16,
+}
+
+impl VlanTag {
+    /// Creates the tag with the lowest priority
+    pub const fn new(identifier: u16) -> Self {
+        Self {
+            priority: 0,
+            drop_eligible: false,
+            identifier,
+        }
+    }
+
+    pub const fn from_tag_control(tag_control: u16) -> Self {
+        Self {
+            priority: (tag_control >> 13) as u8,
+            drop_eligible: tag_control & (1 << 12) != 0,
+            identifier: tag_control & 0xFFF,
+        }
+    }
+
+    pub const fn tag_control(&self) -> u16 {
+        ((self.priority as u16 & 0x7) << 13)
+            | ((self.drop_eligible as u16) << 12)
+            | (self.identifier & 0xFFF)
+    }
+}
+
+/// Returns the VLAN tag of the frame, or None, if the frame is not tagged
+pub fn tag(frame: &[u8]) -> Option<VlanTag> {
+    let tag = frame.get(TAG_OFFSET..TAG_OFFSET + VLAN_TAG_LENGTH)?;
+
+    if u16::from_be_bytes([tag[0], tag[1]]) != VLAN_ETHER_TYPE {
+        return None;
+    }
+
+    Some(VlanTag::from_tag_control(u16::from_be_bytes([
+        tag[2], tag[3],
+    ])))
+}
+
+/// Writes the frame with the VLAN tag into the buffer, which has to be 4 bytes longer
+///
+/// # Panics
+///
+/// If the frame is shorter than the Ethernet header, or the buffer has the wrong length.
+pub fn insert_tag(frame: &[u8], tag: VlanTag, buffer: &mut [u8]) {
+    assert!(frame.len() >= HEADER_LENGTH);
+    assert_eq!(buffer.len(), frame.len() + VLAN_TAG_LENGTH);
+
+    let (addresses, rest) = frame.split_at(TAG_OFFSET);
+
+    buffer[..TAG_OFFSET].copy_from_slice(addresses);
+    buffer[TAG_OFFSET..TAG_OFFSET + 2].copy_from_slice(&VLAN_ETHER_TYPE.to_be_bytes());
+    buffer[TAG_OFFSET + 2..TAG_OFFSET + 4].copy_from_slice(&tag.tag_control().to_be_bytes());
+    buffer[TAG_OFFSET + VLAN_TAG_LENGTH..].copy_from_slice(rest);
+}
+
+/// Removes the VLAN tag from the frame in place, by moving the addresses behind the tag
+///
+/// Returns the tag and the untagged frame, or None, if the frame is not tagged.
+pub fn strip_tag(frame: &mut [u8]) -> Option<(VlanTag, &mut [u8])> {
+    let tag = tag(frame)?;
+
+    frame.copy_within(..TAG_OFFSET, VLAN_TAG_LENGTH);
+
+    Some((tag, &mut frame[VLAN_TAG_LENGTH..]))
+}
@@ -1,13 +1,28 @@
 use core::{cell::UnsafeCell, mem};
 
-use cortex_m::interrupt;
+/// Runs the closure with interrupts disabled
+#[cfg(not(feature = "critical-section"))]
+fn free<R>(f: impl FnOnce() -> R) -> R {
+    cortex_m::interrupt::free(|_| f())
+}
+
+/// Runs the closure inside a critical section of the `critical-section` crate
+#[cfg(feature = "critical-section")]
+fn free<R>(f: impl FnOnce() -> R) -> R {
+    critical_section::with(|_| f())
+}
 
 /// InterruptFreeCell is intended to be used in a static variable.
 /// It synchronises accesses to the inner value by disabling interrupts
 /// while accessing the inner value
 ///
+/// With the `critical-section` feature, accesses are synchronised with the
+/// `critical-section` crate instead, whose implementation is provided by the application.
+/// The `critical-section-single-core` feature provides an implementation,
+/// which disables interrupts.
+///
 /// # Safety
-/// ** This Type should only be used on a single core system **
+/// ** Without the `critical-section` feature, this Type should only be used on a single core system **
 ///
 /// If the system has multiple cores, disabling interrupts is not
 /// sufficient to synchronise access.
@@ -31,7 +46,7 @@ impl<T> InterruptFreeCell<T> {
     /// Sets the content of the cell, and returns the old value
     #[must_use = "If the result of replace is not needed, use set instead"]
     pub fn replace(&self, new_value: T) -> T {
-        interrupt::free(|_| {
+        free(|| {
             // Safety:
             // Accessing the value in the UnsafeCell is safe because no one else is accessing it:
             // The code is running on a single-threaded system, and interrupts have been disabled.
@@ -59,7 +74,7 @@ where
     T: Clone,
 {
     pub fn get(&self) -> T {
-        interrupt::free(|_| {
+        free(|| {
             // Safety:
             // Accessing the value in the UnsafeCell is safe because noone else is accessing it.
             // The code is running on a single-threaded system, and interrupts have been disabled.
@@ -68,3 +83,31 @@ where
         })
     }
 }
+
+impl<T> InterruptFreeCell<Option<T>> {
+    /// Calls the closure with a mutable reference to the content of the cell
+    ///
+    /// Interrupts are disabled while the closure runs, so it should be short.
+    /// Returns None, if the cell is empty, or if the content is already borrowed
+    /// by an outer call of `with` or `try_with`.
+    pub fn try_with<R>(&self, f: impl FnOnce(&mut T) -> R) -> Option<R> {
+        free(|| {
+            // The content is moved out while the closure runs,
+            // so nested calls find the cell empty instead of aliasing the content.
+            let mut value = self.take()?;
+            let result = f(&mut value);
+            self.set(Some(value));
+
+            Some(result)
+        })
+    }
+
+    /// Calls the closure with a mutable reference to the content of the cell
+    ///
+    /// Panics, if the cell is empty, or if the content is already borrowed
+    /// by an outer call of `with` or `try_with`.
+    pub fn with<R>(&self, f: impl FnOnce(&mut T) -> R) -> R {
+        self.try_with(f)
+            .expect("InterruptFreeCell is empty or already borrowed")
+    }
+}
@@ -1,14 +1,19 @@
 #![no_std]
 
 pub mod delay;
-#[cfg(feature = "ethernet")]
+pub mod dwt;
+#[cfg(feature = "ethernet-dma")]
 pub mod ethernet;
 pub mod gpio;
 pub mod interrupt_free_cell;
+pub mod monotonic;
 pub mod prelude;
 pub mod rcc;
+pub mod spsc_queue;
+pub mod system_clock;
 pub mod time;
 pub mod timer;
+pub mod watchdog;
 
 mod sealed {
     pub trait Sealed {}
@@ -0,0 +1,204 @@
+//! Time driver for `embassy-time`
+//!
+//! The tick rate is selected with the `tick-hz-*` features of `embassy-time-driver`.
+//! A single alarm is available, which is enough for one embassy executor.
+//!
+//! The interrupt of the timer passed to [`init`] must call [`on_interrupt`]:
+//!
+//! ```ignore
+//! #[interrupt]
+//! fn TIM2() {
+//!     stm32f207_hal::monotonic::embassy::on_interrupt();
+//! }
+//! ```
+
+use core::{
+    ptr,
+    sync::atomic::{AtomicBool, AtomicPtr, Ordering},
+};
+
+use cortex_m::{interrupt, peripheral::NVIC};
+use embassy_time_driver::{time_driver_impl, AlarmHandle, Driver, TICK_HZ};
+use stm32f2::stm32f217::tim2;
+
+use super::{
+    alarm_pending, clear_alarm, listen_alarm, prescaler, set_alarm_compare, Instance, TimeBase,
+};
+use crate::{
+    interrupt_free_cell::InterruptFreeCell,
+    rcc::{Clocks, APB1},
+};
+
+/// Alarms that are closer than this are armed immediately,
+/// all others are armed by the period interrupts
+const ARM_DISTANCE: u64 = 0xC000_0000;
+
+#[derive(Clone, Copy)]
+struct Callback {
+    function: fn(*mut ()),
+    context: *mut (),
+}
+
+#[derive(Clone, Copy)]
+struct Alarm {
+    timestamp: u64,
+    callback: Option<Callback>,
+}
+
+/// The `embassy-time` driver, which is set up by [`init`]
+pub struct EmbassyTimeDriver {
+    registers: AtomicPtr<tim2::RegisterBlock>,
+    time_base: TimeBase,
+    alarm_allocated: AtomicBool,
+    alarm: InterruptFreeCell<Alarm>,
+}
+
+// Safety:
+// The context pointer of the callback is only passed back to the callback,
+// the driver does not access it by itself.
+unsafe impl Send for EmbassyTimeDriver {}
+
+time_driver_impl!(static DRIVER: EmbassyTimeDriver = EmbassyTimeDriver {
+    registers: AtomicPtr::new(ptr::null_mut()),
+    time_base: TimeBase::new(),
+    alarm_allocated: AtomicBool::new(false),
+    alarm: InterruptFreeCell::new(Alarm {
+        timestamp: u64::MAX,
+        callback: None,
+    }),
+});
+
+/// Starts the time driver on the given timer, and enables its interrupt
+///
+/// Panics, if the driver has already been started,
+/// or if the tick rate can not be derived from the timer clock.
+pub fn init<TIM: Instance>(tim: TIM, clocks: Clocks, apb1: &mut APB1) {
+    // The timer is owned by the driver from now on
+    let _ = tim;
+
+    let registers = TIM::registers() as *const tim2::RegisterBlock as *mut _;
+    let previous = DRIVER.registers.swap(registers, Ordering::AcqRel);
+    assert!(previous.is_null(), "The time driver is already running");
+
+    TIM::enable_and_reset(apb1);
+    DRIVER
+        .time_base
+        .start(TIM::registers(), prescaler(&clocks, TICK_HZ as u32));
+
+    // Safety: The interrupt handler only accesses the driver, which is synchronised
+    unsafe { NVIC::unmask(TIM::INTERRUPT) };
+}
+
+/// Handles the interrupt of the timer passed to [`init`]
+pub fn on_interrupt() {
+    DRIVER.on_interrupt();
+}
+
+impl EmbassyTimeDriver {
+    fn registers(&self) -> Option<&'static tim2::RegisterBlock> {
+        let registers = self.registers.load(Ordering::Acquire);
+
+        // Safety: The pointer is either null, or points to the registers of TIM2 or TIM5
+        unsafe { registers.as_ref() }
+    }
+
+    fn on_interrupt(&self) {
+        let registers = match self.registers() {
+            Some(registers) => registers,
+            None => return,
+        };
+
+        interrupt::free(|_| {
+            if let Some(period) = self.time_base.on_interrupt(registers) {
+                // Arm the alarm, if it falls into the coming period
+                let period_start = (period as u64) << 31;
+                if !alarm_pending(registers)
+                    && self.alarm.get().timestamp < period_start + ARM_DISTANCE
+                {
+                    // The compare flag may still be set by a match in an earlier period
+                    clear_alarm(registers);
+                    listen_alarm(registers, true);
+                }
+            }
+
+            if alarm_pending(registers) {
+                clear_alarm(registers);
+                self.trigger_alarm(registers);
+            }
+        });
+    }
+
+    fn trigger_alarm(&self, registers: &tim2::RegisterBlock) {
+        listen_alarm(registers, false);
+
+        let mut alarm = self.alarm.get();
+        alarm.timestamp = u64::MAX;
+        self.alarm.set(alarm);
+
+        if let Some(callback) = alarm.callback {
+            (callback.function)(callback.context);
+        }
+    }
+}
+
+impl Driver for EmbassyTimeDriver {
+    fn now(&self) -> u64 {
+        match self.registers() {
+            Some(registers) => self.time_base.now(registers),
+            None => 0,
+        }
+    }
+
+    unsafe fn allocate_alarm(&self) -> Option<AlarmHandle> {
+        if self.alarm_allocated.swap(true, Ordering::AcqRel) {
+            return None;
+        }
+
+        Some(AlarmHandle::new(0))
+    }
+
+    fn set_alarm_callback(&self, _alarm: AlarmHandle, callback: fn(*mut ()), ctx: *mut ()) {
+        let mut alarm = self.alarm.get();
+        alarm.callback = Some(Callback {
+            function: callback,
+            context: ctx,
+        });
+        self.alarm.set(alarm);
+    }
+
+    fn set_alarm(&self, _alarm: AlarmHandle, timestamp: u64) -> bool {
+        let registers = match self.registers() {
+            Some(registers) => registers,
+            None => return false,
+        };
+
+        interrupt::free(|_| {
+            let mut alarm = self.alarm.get();
+            alarm.timestamp = timestamp;
+            self.alarm.set(alarm);
+
+            let now = self.now();
+            if timestamp <= now {
+                alarm.timestamp = u64::MAX;
+                self.alarm.set(alarm);
+                return false;
+            }
+
+            // The compare value is set even if the alarm is far away,
+            // so that the period interrupts only need to enable it.
+            set_alarm_compare(registers, timestamp);
+            clear_alarm(registers);
+            listen_alarm(registers, timestamp - now < ARM_DISTANCE);
+
+            // The alarm might have slipped into the past while it was armed
+            if timestamp <= self.now() {
+                listen_alarm(registers, false);
+                alarm.timestamp = u64::MAX;
+                self.alarm.set(alarm);
+                return false;
+            }
+
+            true
+        })
+    }
+}
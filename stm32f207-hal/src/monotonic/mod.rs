@@ -0,0 +1,249 @@
+//! 64-bit monotonic clocks on top of the 32-bit general purpose timers TIM2 and TIM5
+//!
+//! The counter of the timer runs freely from 0 to `u32::MAX`.
+//! Additionally to the update event, capture/compare channel 1 fires in the middle of the counting period,
+//! so a software period counter is incremented twice per overflow.
+//! The parity of the period counter tells whether the hardware counter is expected in the lower or upper half,
+//! which allows reading the extended time without a critical section,
+//! even if an overflow interrupt is still pending.
+//!
+//! Capture/compare channel 2 is used for compare-match alarms.
+
+use core::sync::atomic::{compiler_fence, AtomicU32, Ordering};
+
+use stm32f2::stm32f217::{tim2, Interrupt, TIM2, TIM5};
+
+use crate::{
+    rcc::{Clocks, APB1},
+    sealed::Sealed,
+};
+
+#[cfg(feature = "embassy-time")]
+pub mod embassy;
+#[cfg(feature = "rtic")]
+mod rtic;
+
+/// A 32-bit timer that can be used as the time base of a monotonic clock
+pub trait Instance: Sealed {
+    /// The interrupt that is triggered by the timer
+    const INTERRUPT: Interrupt;
+
+    #[doc(hidden)]
+    fn registers() -> &'static tim2::RegisterBlock;
+
+    #[doc(hidden)]
+    fn enable_and_reset(apb1: &mut APB1);
+}
+
+macro_rules! instances {
+    ($($TIMX:ident, $timXen:ident, $timXrst:ident;)+) => {
+        $(
+            impl Instance for $TIMX {
+                const INTERRUPT: Interrupt = Interrupt::$TIMX;
+
+                fn registers() -> &'static tim2::RegisterBlock {
+                    // Safety: TIM2 and TIM5 have the same register layout,
+                    // the register block of TIM2 is only used to describe it.
+                    unsafe { &*($TIMX::ptr() as *const tim2::RegisterBlock) }
+                }
+
+                fn enable_and_reset(apb1: &mut APB1) {
+                    apb1.enr().modify(|_, w| w.$timXen().set_bit());
+                    apb1.rstr().modify(|_, w| w.$timXrst().set_bit());
+                    apb1.rstr().modify(|_, w| w.$timXrst().clear_bit());
+                }
+            }
+        )+
+    };
+}
+
+instances! {
+    TIM2, tim2en, tim2rst;
+    TIM5, tim5en, tim5rst;
+}
+
+const HALF_PERIOD: u32 = 1 << 31;
+
+const UPDATE_FLAG: u32 = 1 << 0;
+const HALF_PERIOD_FLAG: u32 = 1 << 1;
+const ALARM_FLAG: u32 = 1 << 2;
+
+/// Extends the 32-bit counter of a timer to 64 bits
+pub(crate) struct TimeBase {
+    period: AtomicU32,
+}
+
+impl TimeBase {
+    pub(crate) const fn new() -> Self {
+        Self {
+            period: AtomicU32::new(0),
+        }
+    }
+
+    /// Configures the timer to count freely with the given prescaler, and starts it
+    pub(crate) fn start(&self, registers: &tim2::RegisterBlock, prescaler: u16) {
+        // pause timer
+        registers.cr1.modify(|_, w| w.cen().clear_bit());
+
+        self.period.store(0, Ordering::Relaxed);
+
+        registers.psc.write(|w| w.psc().bits(prescaler));
+        registers.arr.write(|w| w.arr().bits(u32::MAX));
+        registers.ccr1.write(|w| w.ccr().bits(HALF_PERIOD));
+
+        // Only overflows should set the update flag, not the update generation below
+        registers.cr1.modify(|_, w| w.urs().set_bit());
+        // Load the prescaler and restart the counter
+        registers.egr.write(|w| w.ug().set_bit());
+        registers.cnt.reset();
+        registers.sr.write(|w| unsafe { w.bits(0) });
+
+        registers
+            .dier
+            .modify(|_, w| w.uie().set_bit().cc1ie().set_bit());
+
+        // start timer
+        registers.cr1.modify(|_, w| w.cen().set_bit());
+    }
+
+    pub(crate) fn stop(&self, registers: &tim2::RegisterBlock) {
+        registers.cr1.modify(|_, w| w.cen().clear_bit());
+        registers.dier.write(|w| unsafe { w.bits(0) });
+    }
+
+    /// Returns the ticks since the timer was started
+    pub(crate) fn now(&self, registers: &tim2::RegisterBlock) -> u64 {
+        let period = self.period.load(Ordering::Relaxed);
+        // The period has to be read before the counter
+        compiler_fence(Ordering::Acquire);
+        let counter = registers.cnt.read().bits();
+
+        ((period as u64) << 31) + ((counter ^ ((period & 1) << 31)) as u64)
+    }
+
+    /// Handles the overflow and half period events
+    ///
+    /// Returns the number of the current period, if it has changed
+    pub(crate) fn on_interrupt(&self, registers: &tim2::RegisterBlock) -> Option<u32> {
+        let status = registers.sr.read();
+
+        let mut flags = 0;
+        if status.uif().bit_is_set() {
+            flags |= UPDATE_FLAG;
+        }
+        if status.cc1if().bit_is_set() {
+            flags |= HALF_PERIOD_FLAG;
+        }
+
+        if flags == 0 {
+            return None;
+        }
+
+        clear_flags(registers, flags);
+
+        let increment = flags.count_ones();
+        Some(self.period.fetch_add(increment, Ordering::Relaxed) + increment)
+    }
+}
+
+/// Clears the given flags in the status register without affecting the others
+///
+/// The status flags are cleared by writing zero, writing one has no effect
+fn clear_flags(registers: &tim2::RegisterBlock, flags: u32) {
+    registers.sr.write(|w| unsafe { w.bits(0xFFFF & !flags) });
+}
+
+fn set_alarm_compare(registers: &tim2::RegisterBlock, timestamp: u64) {
+    registers.ccr2.write(|w| w.ccr().bits(timestamp as u32));
+}
+
+fn listen_alarm(registers: &tim2::RegisterBlock, enable: bool) {
+    registers.dier.modify(|_, w| w.cc2ie().bit(enable));
+}
+
+/// Returns true, if the alarm is enabled and has fired
+fn alarm_pending(registers: &tim2::RegisterBlock) -> bool {
+    registers.dier.read().cc2ie().bit_is_set() && registers.sr.read().cc2if().bit_is_set()
+}
+
+fn clear_alarm(registers: &tim2::RegisterBlock) {
+    clear_flags(registers, ALARM_FLAG);
+}
+
+/// Calculates the prescaler, so that the timer counts with the given frequency
+fn prescaler(clocks: &Clocks, frequency: u32) -> u16 {
+    let timer_clock = clocks.timclk1().0;
+
+    let prescaler = timer_clock / frequency - 1;
+
+    assert!(
+        (prescaler + 1) * frequency == timer_clock,
+        "The timer clock must be a multiple of the tick rate"
+    );
+    assert!(prescaler <= u16::MAX as u32, "The tick rate is too low");
+
+    prescaler as u16
+}
+
+/// A monotonic clock with 64-bit ticks at the frequency `FREQ`
+///
+/// The interrupt of the timer must be handled by calling [`MonoTimer::on_interrupt`],
+/// unless the timer is used by RTIC, which does that by itself.
+pub struct MonoTimer<TIM, const FREQ: u32> {
+    tim: TIM,
+    time_base: TimeBase,
+}
+
+impl<TIM: Instance, const FREQ: u32> MonoTimer<TIM, FREQ> {
+    /// Creates and starts the monotonic clock
+    ///
+    /// Panics, if `FREQ` can not be derived from the timer clock.
+    pub fn new(tim: TIM, clocks: Clocks, apb1: &mut APB1) -> Self {
+        TIM::enable_and_reset(apb1);
+
+        let time_base = TimeBase::new();
+        time_base.start(TIM::registers(), prescaler(&clocks, FREQ));
+
+        Self { tim, time_base }
+    }
+
+    /// Returns the ticks since the clock was started
+    pub fn now_ticks(&self) -> u64 {
+        self.time_base.now(TIM::registers())
+    }
+
+    /// Sets the alarm to the given tick
+    ///
+    /// Only the lower 32 bits of the timestamp are compared,
+    /// so the alarm may fire early if it is further than `u32::MAX` ticks in the future.
+    pub fn set_alarm(&mut self, timestamp: u64) {
+        set_alarm_compare(TIM::registers(), timestamp);
+        listen_alarm(TIM::registers(), true);
+    }
+
+    /// Disables the alarm
+    pub fn cancel_alarm(&mut self) {
+        listen_alarm(TIM::registers(), false);
+        clear_alarm(TIM::registers());
+    }
+
+    /// Handles the timer interrupt
+    ///
+    /// Returns true, if the alarm has fired.
+    pub fn on_interrupt(&mut self) -> bool {
+        self.time_base.on_interrupt(TIM::registers());
+
+        let fired = alarm_pending(TIM::registers());
+        if fired {
+            clear_alarm(TIM::registers());
+        }
+
+        fired
+    }
+
+    /// Stops the timer and releases it
+    pub fn release(self) -> TIM {
+        self.time_base.stop(TIM::registers());
+        self.tim
+    }
+}
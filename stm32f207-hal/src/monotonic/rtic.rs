@@ -0,0 +1,47 @@
+use fugit::{TimerDurationU64, TimerInstantU64};
+use rtic_monotonic::Monotonic;
+
+use super::{clear_alarm, listen_alarm, set_alarm_compare, Instance, MonoTimer};
+
+impl<TIM: Instance, const FREQ: u32> Monotonic for MonoTimer<TIM, FREQ> {
+    // The overflow interrupts are needed to extend the counter
+    const DISABLE_INTERRUPT_ON_EMPTY_QUEUE: bool = false;
+
+    type Instant = TimerInstantU64<FREQ>;
+    type Duration = TimerDurationU64<FREQ>;
+
+    fn now(&mut self) -> Self::Instant {
+        Self::Instant::from_ticks(self.now_ticks())
+    }
+
+    fn set_compare(&mut self, instant: Self::Instant) {
+        // RTIC checks the timer queue on every interrupt,
+        // so an alarm further in the future than a period only causes an early wakeup
+        set_alarm_compare(TIM::registers(), instant.ticks());
+    }
+
+    fn clear_compare_flag(&mut self) {
+        clear_alarm(TIM::registers());
+    }
+
+    fn zero() -> Self::Instant {
+        Self::Instant::from_ticks(0)
+    }
+
+    unsafe fn reset(&mut self) {
+        let prescaler = TIM::registers().psc.read().psc().bits();
+        self.time_base.start(TIM::registers(), prescaler);
+    }
+
+    fn on_interrupt(&mut self) {
+        self.time_base.on_interrupt(TIM::registers());
+    }
+
+    fn enable_timer(&mut self) {
+        listen_alarm(TIM::registers(), true);
+    }
+
+    fn disable_timer(&mut self) {
+        listen_alarm(TIM::registers(), false);
+    }
+}
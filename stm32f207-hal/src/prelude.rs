@@ -2,6 +2,7 @@
 pub use embedded_hal::prelude::*;
 
 // Bring the extendions into scope
+pub use crate::dwt::DwtExtension as _stm32f217_hal_dwt_DwtExtension;
 pub use crate::gpio::gpio_extension::GpioExtension as _stm32f217_hal_gpio_GpioExtension;
 pub use crate::rcc::RccExtension as _stm32f217_hal_rcc_RccExtension;
 pub use crate::time::U32Ext as _stm32f217_hal_time_U32Extension;
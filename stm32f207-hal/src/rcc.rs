@@ -113,6 +113,9 @@ pub struct CFGR {
 
 pub const HSI: u32 = 8_000_000;
 
+/// Typical frequency of the low speed internal oscillator, which clocks the independent watchdog
+pub const LSI: u32 = 32_000;
+
 impl CFGR {
     pub fn hclk(mut self, freq: impl Into<Hertz>) -> Self {
         self.hclk = Some(freq.into().0);
@@ -294,4 +297,24 @@ impl Clocks {
     pub fn sysclk(&self) -> Hertz {
         self.sysclk
     }
+
+    /// Returns the frequency of the timers connected to the APB1
+    ///
+    /// If the APB1 prescaler is not 1, the timer clock is twice the frequency of the APB1
+    pub fn timclk1(&self) -> Hertz {
+        match self.ppre1 {
+            1 => self.pclk1,
+            _ => Hertz(self.pclk1.0 * 2),
+        }
+    }
+
+    /// Returns the frequency of the timers connected to the APB2
+    ///
+    /// If the APB2 prescaler is not 1, the timer clock is twice the frequency of the APB2
+    pub fn timclk2(&self) -> Hertz {
+        match self.ppre2 {
+            1 => self.pclk2,
+            _ => Hertz(self.pclk2.0 * 2),
+        }
+    }
 }
@@ -0,0 +1,214 @@
+//! A lock-free single producer, single consumer queue
+//!
+//! The queue is split into a [`Producer`] and a [`Consumer`],
+//! which can be moved to different contexts, e.g. an interrupt handler and the main loop.
+//! Both halves only use atomics for synchronisation, interrupts are never disabled.
+//!
+//! ```ignore
+//! static EVENTS: Queue<Event, 16> = Queue::new();
+//!
+//! let (producer, consumer) = EVENTS.split();
+//! ```
+
+use core::{
+    cell::UnsafeCell,
+    mem::MaybeUninit,
+    ptr,
+    sync::atomic::{AtomicBool, AtomicUsize, Ordering},
+};
+
+/// A queue with space for `N` elements
+///
+/// The queue is intended to be used in a static variable.
+pub struct Queue<T, const N: usize> {
+    buffer: UnsafeCell<MaybeUninit<[T; N]>>,
+    // The indices run from 0 to 2N - 1, so that a full queue can be told apart from an empty one.
+    // Only the consumer writes the head, and only the producer writes the tail.
+    head: AtomicUsize,
+    tail: AtomicUsize,
+    split: AtomicBool,
+}
+
+// Safety:
+// The producer only writes empty slots, and the consumer only reads full slots.
+// The slots are handed over between them with the head and tail indices.
+unsafe impl<T: Send, const N: usize> Sync for Queue<T, N> {}
+
+impl<T, const N: usize> Queue<T, N> {
+    pub const fn new() -> Self {
+        Self {
+            buffer: UnsafeCell::new(MaybeUninit::uninit()),
+            head: AtomicUsize::new(0),
+            tail: AtomicUsize::new(0),
+            split: AtomicBool::new(false),
+        }
+    }
+
+    /// Splits the queue into the producer and the consumer half
+    ///
+    /// Panics, if the queue has already been split.
+    pub fn split(&'static self) -> (Producer<T, N>, Consumer<T, N>) {
+        assert!(N > 0, "The capacity of the queue must not be zero");
+
+        let already_split = self.split.swap(true, Ordering::AcqRel);
+        assert!(!already_split, "The queue has already been split");
+
+        (Producer { queue: self }, Consumer { queue: self })
+    }
+
+    pub const fn capacity(&self) -> usize {
+        N
+    }
+
+    /// Returns the number of elements in the queue
+    pub fn len(&self) -> usize {
+        let head = self.head.load(Ordering::Acquire);
+        let tail = self.tail.load(Ordering::Acquire);
+
+        (tail + 2 * N - head) % (2 * N)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.len() == N
+    }
+
+    fn next(index: usize) -> usize {
+        if index + 1 == 2 * N {
+            0
+        } else {
+            index + 1
+        }
+    }
+
+    fn slot(&self, index: usize) -> *mut T {
+        let buffer = self.buffer.get() as *mut T;
+
+        // Safety: The slot lies inside of the buffer
+        unsafe { buffer.add(index % N) }
+    }
+}
+
+impl<T, const N: usize> Default for Queue<T, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T, const N: usize> Drop for Queue<T, N> {
+    fn drop(&mut self) {
+        let mut head = *self.head.get_mut();
+        let tail = *self.tail.get_mut();
+
+        while head != tail {
+            // Safety: The slots between head and tail are initialised
+            unsafe { ptr::drop_in_place(self.slot(head)) };
+            head = Self::next(head);
+        }
+    }
+}
+
+/// The half of a [`Queue`], which adds elements
+pub struct Producer<T: 'static, const N: usize> {
+    queue: &'static Queue<T, N>,
+}
+
+// Safety: The producer is the only one adding elements
+unsafe impl<T: Send, const N: usize> Send for Producer<T, N> {}
+
+impl<T, const N: usize> Producer<T, N> {
+    /// Adds an element at the end of the queue
+    ///
+    /// Returns the element, if the queue is full.
+    pub fn enqueue(&mut self, item: T) -> Result<(), T> {
+        let tail = self.queue.tail.load(Ordering::Relaxed);
+
+        if self.queue.is_full() {
+            return Err(item);
+        }
+
+        // Safety: The slot is empty, and the consumer does not access it until the tail is advanced
+        unsafe { self.queue.slot(tail).write(item) };
+        self.queue
+            .tail
+            .store(Queue::<T, N>::next(tail), Ordering::Release);
+
+        Ok(())
+    }
+
+    pub fn len(&self) -> usize {
+        self.queue.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.queue.is_empty()
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.queue.is_full()
+    }
+
+    pub const fn capacity(&self) -> usize {
+        N
+    }
+}
+
+/// The half of a [`Queue`], which removes elements
+pub struct Consumer<T: 'static, const N: usize> {
+    queue: &'static Queue<T, N>,
+}
+
+// Safety: The consumer is the only one removing elements
+unsafe impl<T: Send, const N: usize> Send for Consumer<T, N> {}
+
+impl<T, const N: usize> Consumer<T, N> {
+    /// Removes the element at the front of the queue
+    ///
+    /// Returns None, if the queue is empty.
+    pub fn dequeue(&mut self) -> Option<T> {
+        let head = self.queue.head.load(Ordering::Relaxed);
+
+        if self.queue.is_empty() {
+            return None;
+        }
+
+        // Safety: The slot is full, and the producer does not access it until the head is advanced
+        let item = unsafe { self.queue.slot(head).read() };
+        self.queue
+            .head
+            .store(Queue::<T, N>::next(head), Ordering::Release);
+
+        Some(item)
+    }
+
+    /// Returns a reference to the element at the front of the queue, without removing it
+    pub fn peek(&self) -> Option<&T> {
+        let head = self.queue.head.load(Ordering::Relaxed);
+
+        if self.queue.is_empty() {
+            return None;
+        }
+
+        // Safety: The slot is full, and stays full until the consumer removes the element
+        Some(unsafe { &*self.queue.slot(head) })
+    }
+
+    pub fn len(&self) -> usize {
+        self.queue.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.queue.is_empty()
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.queue.is_full()
+    }
+
+    pub const fn capacity(&self) -> usize {
+        N
+    }
+}
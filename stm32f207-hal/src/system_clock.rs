@@ -0,0 +1,78 @@
+//! A system wide millisecond clock
+//!
+//! The clock is advanced by calling [`tick`] once per millisecond from an interrupt handler.
+//! [`SysTickClock`] configures SysTick for this:
+//!
+//! ```ignore
+//! #[exception]
+//! fn SysTick() {
+//!     stm32f207_hal::system_clock::tick();
+//! }
+//! ```
+//!
+//! Alternatively, any timer can drive the clock: Start it with 1 kHz, listen to `Event::TimeOut`,
+//! and call [`tick`] in its interrupt handler after clearing the event.
+//!
+//! The clock can be read from thread mode and from interrupts.
+
+use cortex_m::{
+    interrupt,
+    peripheral::{syst::SystClkSource, SYST},
+};
+
+use crate::{interrupt_free_cell::InterruptFreeCell, rcc::Clocks, time::Instant};
+
+static MILLIS: InterruptFreeCell<u64> = InterruptFreeCell::new(0);
+
+/// Advances the clock by one millisecond
+///
+/// Must be called once per millisecond, from the interrupt driving the clock.
+pub fn tick() {
+    interrupt::free(|_| {
+        let millis = MILLIS.get();
+        MILLIS.set(millis + 1);
+    });
+}
+
+/// Returns the milliseconds since the clock was started
+pub fn millis() -> u64 {
+    MILLIS.get()
+}
+
+/// Returns the time since the clock was started
+pub fn instant() -> Instant {
+    Instant::from_millis(millis())
+}
+
+/// Returns the time since the clock was started, to be used as timestamp for smoltcp
+#[cfg(feature = "smoltcp")]
+pub fn now() -> smoltcp::time::Instant {
+    smoltcp::time::Instant::from_millis(millis() as i64)
+}
+
+/// Drives the system clock with the SysTick interrupt
+pub struct SysTickClock {
+    syst: SYST,
+}
+
+impl SysTickClock {
+    /// Starts SysTick with a period of one millisecond, and enables its interrupt
+    pub fn new(mut syst: SYST, clocks: Clocks) -> Self {
+        // SysTick counts with the core clock, and is reloaded after reaching zero
+        let reload = clocks.hclk().0 / 1_000 - 1;
+
+        syst.set_clock_source(SystClkSource::Core);
+        syst.set_reload(reload);
+        syst.clear_current();
+        syst.enable_interrupt();
+        syst.enable_counter();
+
+        Self { syst }
+    }
+
+    pub fn release(mut self) -> SYST {
+        self.syst.disable_interrupt();
+        self.syst.disable_counter();
+        self.syst
+    }
+}
@@ -1,5 +1,7 @@
 //! taken from stm32f4xx-hal
 
+use core::ops::{Add, AddAssign, Sub, SubAssign};
+
 /// Bits per second
 #[derive(PartialEq, PartialOrd, Clone, Copy, Debug)]
 pub struct Bps(pub u32);
@@ -29,6 +31,12 @@ pub trait U32Ext {
 
     /// Wrap in `MilliSeconds`
     fn ms(self) -> MilliSeconds;
+
+    /// Wrap in `MicroSeconds`
+    fn us(self) -> MicroSeconds;
+
+    /// Wrap in `Seconds`
+    fn secs(self) -> Seconds;
 }
 
 impl U32Ext for u32 {
@@ -51,6 +59,14 @@ impl U32Ext for u32 {
     fn ms(self) -> MilliSeconds {
         MilliSeconds(self)
     }
+
+    fn us(self) -> MicroSeconds {
+        MicroSeconds(self)
+    }
+
+    fn secs(self) -> Seconds {
+        Seconds(self)
+    }
 }
 
 impl From<u32> for Hertz {
@@ -77,6 +93,320 @@ impl From<MegaHertz> for KiloHertz {
     }
 }
 
+impl Hertz {
+    /// Returns the duration of one period, rounded down to microseconds
+    ///
+    /// Panics, if the frequency is zero or above 1 MHz, because the period would be zero.
+    /// Use `Period::Frequency` for higher frequencies, e.g. for a timer.
+    pub fn period(self) -> Duration {
+        assert!(
+            self.0 > 0 && self.0 <= 1_000_000,
+            "The period is only defined for frequencies between 1 Hz and 1 MHz"
+        );
+
+        Duration::from_micros(1_000_000 / self.0 as u64)
+    }
+}
+
 /// Time unit
 #[derive(PartialEq, PartialOrd, Clone, Copy, Debug)]
 pub struct MilliSeconds(pub u32);
+
+/// Time unit
+#[derive(PartialEq, PartialOrd, Clone, Copy, Debug)]
+pub struct MicroSeconds(pub u32);
+
+/// Time unit
+#[derive(PartialEq, PartialOrd, Clone, Copy, Debug)]
+pub struct Seconds(pub u32);
+
+/// A span of time, with a resolution of microseconds
+#[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Debug, Default)]
+pub struct Duration {
+    micros: u64,
+}
+
+impl Duration {
+    pub const ZERO: Duration = Duration::from_micros(0);
+
+    pub const fn from_micros(micros: u64) -> Self {
+        Self { micros }
+    }
+
+    pub const fn from_millis(millis: u64) -> Self {
+        Self::from_micros(millis * 1_000)
+    }
+
+    pub const fn from_secs(secs: u64) -> Self {
+        Self::from_micros(secs * 1_000_000)
+    }
+
+    pub const fn as_micros(&self) -> u64 {
+        self.micros
+    }
+
+    pub const fn as_millis(&self) -> u64 {
+        self.micros / 1_000
+    }
+
+    pub const fn as_secs(&self) -> u64 {
+        self.micros / 1_000_000
+    }
+
+    /// Returns the frequency with this duration as period, rounded down to Hertz
+    ///
+    /// Panics, if the duration is zero
+    pub fn frequency(self) -> Hertz {
+        Hertz((1_000_000 / self.micros) as u32)
+    }
+
+    /// Returns the number of cycles of a clock with the given frequency during this duration
+    pub fn cycles(self, clock: Hertz) -> u64 {
+        self.micros * clock.0 as u64 / 1_000_000
+    }
+
+    pub fn checked_add(self, rhs: Duration) -> Option<Duration> {
+        self.micros.checked_add(rhs.micros).map(Duration::from_micros)
+    }
+
+    pub fn checked_sub(self, rhs: Duration) -> Option<Duration> {
+        self.micros.checked_sub(rhs.micros).map(Duration::from_micros)
+    }
+}
+
+impl From<MicroSeconds> for Duration {
+    fn from(us: MicroSeconds) -> Self {
+        Self::from_micros(us.0 as u64)
+    }
+}
+
+impl From<MilliSeconds> for Duration {
+    fn from(ms: MilliSeconds) -> Self {
+        Self::from_millis(ms.0 as u64)
+    }
+}
+
+impl From<Seconds> for Duration {
+    fn from(s: Seconds) -> Self {
+        Self::from_secs(s.0 as u64)
+    }
+}
+
+impl Add for Duration {
+    type Output = Duration;
+
+    fn add(self, rhs: Duration) -> Duration {
+        Duration::from_micros(self.micros + rhs.micros)
+    }
+}
+
+impl AddAssign for Duration {
+    fn add_assign(&mut self, rhs: Duration) {
+        *self = *self + rhs;
+    }
+}
+
+impl Sub for Duration {
+    type Output = Duration;
+
+    fn sub(self, rhs: Duration) -> Duration {
+        Duration::from_micros(self.micros - rhs.micros)
+    }
+}
+
+impl SubAssign for Duration {
+    fn sub_assign(&mut self, rhs: Duration) {
+        *self = *self - rhs;
+    }
+}
+
+/// A point in time, measured in microseconds since an arbitrary epoch,
+/// e.g. the start of a clock
+#[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Debug, Default)]
+pub struct Instant {
+    micros: u64,
+}
+
+impl Instant {
+    pub const fn from_micros(micros: u64) -> Self {
+        Self { micros }
+    }
+
+    pub const fn from_millis(millis: u64) -> Self {
+        Self::from_micros(millis * 1_000)
+    }
+
+    pub const fn as_micros(&self) -> u64 {
+        self.micros
+    }
+
+    pub const fn as_millis(&self) -> u64 {
+        self.micros / 1_000
+    }
+
+    /// Returns the duration since the epoch
+    pub const fn duration_since_epoch(&self) -> Duration {
+        Duration::from_micros(self.micros)
+    }
+
+    /// Returns the duration elapsed since the earlier instant
+    ///
+    /// Returns None, if `earlier` is later than this instant
+    pub fn checked_duration_since(&self, earlier: Instant) -> Option<Duration> {
+        self.micros.checked_sub(earlier.micros).map(Duration::from_micros)
+    }
+}
+
+impl Add<Duration> for Instant {
+    type Output = Instant;
+
+    fn add(self, rhs: Duration) -> Instant {
+        Instant::from_micros(self.micros + rhs.micros)
+    }
+}
+
+impl AddAssign<Duration> for Instant {
+    fn add_assign(&mut self, rhs: Duration) {
+        *self = *self + rhs;
+    }
+}
+
+impl Sub<Duration> for Instant {
+    type Output = Instant;
+
+    fn sub(self, rhs: Duration) -> Instant {
+        Instant::from_micros(self.micros - rhs.micros)
+    }
+}
+
+impl SubAssign<Duration> for Instant {
+    fn sub_assign(&mut self, rhs: Duration) {
+        *self = *self - rhs;
+    }
+}
+
+impl Sub for Instant {
+    type Output = Duration;
+
+    fn sub(self, rhs: Instant) -> Duration {
+        Duration::from_micros(self.micros - rhs.micros)
+    }
+}
+
+/// The timeout of a timer, given either as frequency or as period
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum Period {
+    Frequency(Hertz),
+    Duration(Duration),
+}
+
+impl Period {
+    /// Returns the number of cycles of a clock with the given frequency during one period
+    pub fn cycles(self, clock: Hertz) -> u64 {
+        match self {
+            Period::Frequency(frequency) => (clock.0 / frequency.0) as u64,
+            Period::Duration(duration) => duration.cycles(clock),
+        }
+    }
+}
+
+impl From<Hertz> for Period {
+    fn from(frequency: Hertz) -> Self {
+        Period::Frequency(frequency)
+    }
+}
+
+impl From<KiloHertz> for Period {
+    fn from(frequency: KiloHertz) -> Self {
+        Period::Frequency(frequency.into())
+    }
+}
+
+impl From<MegaHertz> for Period {
+    fn from(frequency: MegaHertz) -> Self {
+        Period::Frequency(frequency.into())
+    }
+}
+
+impl From<Duration> for Period {
+    fn from(duration: Duration) -> Self {
+        Period::Duration(duration)
+    }
+}
+
+impl From<MicroSeconds> for Period {
+    fn from(us: MicroSeconds) -> Self {
+        Period::Duration(us.into())
+    }
+}
+
+impl From<MilliSeconds> for Period {
+    fn from(ms: MilliSeconds) -> Self {
+        Period::Duration(ms.into())
+    }
+}
+
+impl From<Seconds> for Period {
+    fn from(s: Seconds) -> Self {
+        Period::Duration(s.into())
+    }
+}
+
+#[cfg(feature = "fugit")]
+mod fugit_interop {
+    use super::{Duration, Hertz, Instant, Period};
+
+    impl<const NOM: u32, const DENOM: u32> From<fugit::Duration<u32, NOM, DENOM>> for Duration {
+        fn from(duration: fugit::Duration<u32, NOM, DENOM>) -> Self {
+            Duration::from_micros(duration.to_micros() as u64)
+        }
+    }
+
+    impl<const NOM: u32, const DENOM: u32> From<fugit::Duration<u64, NOM, DENOM>> for Duration {
+        fn from(duration: fugit::Duration<u64, NOM, DENOM>) -> Self {
+            Duration::from_micros(duration.to_micros())
+        }
+    }
+
+    impl From<Duration> for fugit::MicrosDurationU64 {
+        fn from(duration: Duration) -> Self {
+            fugit::MicrosDurationU64::from_ticks(duration.as_micros())
+        }
+    }
+
+    impl<const NOM: u32, const DENOM: u32> From<fugit::Instant<u64, NOM, DENOM>> for Instant {
+        fn from(instant: fugit::Instant<u64, NOM, DENOM>) -> Self {
+            Instant::from_micros(instant.duration_since_epoch().to_micros())
+        }
+    }
+
+    impl From<Instant> for fugit::TimerInstantU64<1_000_000> {
+        fn from(instant: Instant) -> Self {
+            fugit::TimerInstantU64::from_ticks(instant.as_micros())
+        }
+    }
+
+    impl<const NOM: u32, const DENOM: u32> From<fugit::Rate<u32, NOM, DENOM>> for Hertz {
+        fn from(rate: fugit::Rate<u32, NOM, DENOM>) -> Self {
+            Hertz(rate.to_Hz())
+        }
+    }
+
+    impl From<Hertz> for fugit::HertzU32 {
+        fn from(frequency: Hertz) -> Self {
+            fugit::HertzU32::from_raw(frequency.0)
+        }
+    }
+
+    impl<const NOM: u32, const DENOM: u32> From<fugit::Duration<u32, NOM, DENOM>> for Period {
+        fn from(duration: fugit::Duration<u32, NOM, DENOM>) -> Self {
+            Period::Duration(duration.into())
+        }
+    }
+
+    impl<const NOM: u32, const DENOM: u32> From<fugit::Rate<u32, NOM, DENOM>> for Period {
+        fn from(rate: fugit::Rate<u32, NOM, DENOM>) -> Self {
+            Period::Frequency(rate.into())
+        }
+    }
+}
@@ -1,8 +1,9 @@
 use crate::{
     rcc::{Clocks, APB1, APB2},
-    time::Hertz,
+    sealed::Sealed,
+    time::Period,
 };
-use embedded_hal::timer::{CountDown, Periodic};
+use embedded_hal::timer::{Cancel, CountDown, Periodic};
 use nb;
 use stm32f2::stm32f217::{
     TIM10, TIM11, TIM12, TIM13, TIM14, TIM2, TIM3, TIM4, TIM5, TIM6, TIM7, TIM9,
@@ -16,58 +17,118 @@ pub struct Timer<TIM> {
 }
 
 /// Interrupt events
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum Event {
     /// Timer timed out / count down ended
     TimeOut,
+    /// Capture/compare event on channel 1
+    CaptureCompare1,
+    /// Capture/compare event on channel 2
+    CaptureCompare2,
+    /// Capture/compare event on channel 3
+    CaptureCompare3,
+    /// Capture/compare event on channel 4
+    CaptureCompare4,
+    /// Trigger event of the slave mode controller
+    Trigger,
 }
 
+impl Event {
+    /// Returns the bit of the event,
+    /// which is the same in the DIER and SR registers of all timers
+    fn mask(self) -> u32 {
+        match self {
+            Event::TimeOut => 1 << 0,
+            Event::CaptureCompare1 => 1 << 1,
+            Event::CaptureCompare2 => 1 << 2,
+            Event::CaptureCompare3 => 1 << 3,
+            Event::CaptureCompare4 => 1 << 4,
+            Event::Trigger => 1 << 6,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum Error {
+    /// The timer is not running
+    Disabled,
+}
+
+const EVENTS_BASIC: u32 = 0b000_0001;
+const EVENTS_1_CHANNEL: u32 = 0b000_0011;
+const EVENTS_2_CHANNELS: u32 = 0b100_0111;
+const EVENTS_4_CHANNELS: u32 = 0b101_1111;
+
 macro_rules! timers {
-    ($($TIMX:ident, $timX:ident, $timXen:ident, $timXrst:ident, $APBX:ident, $apbX:ident;)+) => {
+    ($($TIMX:ident, $timX:ident, $timXen:ident, $timXrst:ident, $APBX:ident, $apbX:ident, $timclkX:ident, $events:ident;)+) => {
         $(
+            impl Sealed for $TIMX {}
+
             impl Periodic for Timer<$TIMX> {}
 
+            impl Cancel for Timer<$TIMX> {
+                type Error = Error;
+
+                fn cancel(&mut self) -> Result<(), Self::Error> {
+                    if self.tim.cr1.read().cen().bit_is_clear() {
+                        return Err(Error::Disabled);
+                    }
+
+                    // pause timer
+                    self.tim.cr1.modify(|_, w| w.cen().clear_bit());
+                    self.clear_interrupt(Event::TimeOut);
+
+                    Ok(())
+                }
+            }
+
             impl CountDown for Timer<$TIMX> {
-                type Time = Hertz;
+                type Time = Period;
 
                 // NOTE(allow) `w.psc().bits()` is safe for some timers but not for others because of
                 // some SVD omission
                 #[allow(unused_unsafe)]
                 fn start<T>(&mut self, timeout: T)
-                    where T: Into<Hertz>,
+                    where T: Into<Period>,
                 {
                     // pause timer
                     self.tim.cr1.modify(|_, w| w.cen().clear_bit());
                     // restart counter
                     self.tim.cnt.reset();
 
-                    let frequency = timeout.into().0;
-
                     // Calculate required number of ticks
-                    let ticks = self.clocks.pclk1().0 * (self.clocks.ppre1() as u32) / frequency;
+                    let ticks = timeout.into().cycles(self.clocks.$timclkX());
+                    assert!(ticks > 0 && ticks <= 1 << 32, "The timeout is out of range for the timer");
 
                     // Setup prescaler and Auto-Reload
                     let prescaler = (ticks - 1) / (1 << 16);
                     self.tim.psc.write(|w| unsafe {w.psc().bits(prescaler as u16)});
 
-                    let auto_reload = ticks / (prescaler + 1);
-                    self.tim.arr.write(|w| unsafe { w.bits(auto_reload)});
+                    // The counter runs from 0 to the auto-reload value, so a period lasts one tick longer
+                    let auto_reload = ticks / (prescaler + 1) - 1;
+                    self.tim.arr.write(|w| unsafe { w.bits(auto_reload as u32)});
+
+                    // Load the prescaler immediately, without raising the update flag
+                    self.tim.cr1.modify(|_, w| w.urs().set_bit());
+                    self.tim.egr.write(|w| w.ug().set_bit());
+                    self.clear_interrupt(Event::TimeOut);
 
                     // start timer
                     self.tim.cr1.modify(|_, w| w.cen().set_bit());
                 }
 
                 fn wait(&mut self) -> nb::Result<(), Void> {
-                    if self.tim.sr.read().uif().bit_is_clear() {
-                        Err(nb::Error::WouldBlock)
-                    } else {
-                        self.tim.sr.modify(|_, w| w.uif().clear_bit());
+                    if self.is_pending(Event::TimeOut) {
+                        self.clear_interrupt(Event::TimeOut);
                         Ok(())
+                    } else {
+                        Err(nb::Error::WouldBlock)
                     }
                 }
             }
 
             impl Timer<$TIMX> {
-                pub fn new(tim: $TIMX, timeout: impl Into<Hertz>, clocks: Clocks, $apbX: &mut $APBX) -> Self
+                pub fn new(tim: $TIMX, timeout: impl Into<Period>, clocks: Clocks, $apbX: &mut $APBX) -> Self
                 {
                     // enable and reset timer
                     $apbX.enr().modify(|_, w| w.$timXen().set_bit());
@@ -84,22 +145,50 @@ macro_rules! timers {
                     timer
                 }
 
+                /// Enables the interrupt for the event
+                ///
+                /// Panics, if the timer does not support the event
                 pub fn listen(&mut self, event: Event) {
-                    match event {
-                        Event::TimeOut => {
-                            // Enable update event interrupt
-                            self.tim.dier.write(|w| w.uie().set_bit());
-                        }
-                    }
+                    let mask = Self::supported_mask(event);
+
+                    self.tim.dier.modify(|r, w| unsafe { w.bits(r.bits() | mask) });
                 }
 
+                /// Disables the interrupt for the event
+                ///
+                /// Panics, if the timer does not support the event
                 pub fn unlisten(&mut self, event: Event) {
-                    match event {
-                        Event::TimeOut => {
-                            // Enable update event interrupt
-                            self.tim.dier.write(|w| w.uie().clear_bit());
-                        }
-                    }
+                    let mask = Self::supported_mask(event);
+
+                    self.tim.dier.modify(|r, w| unsafe { w.bits(r.bits() & !mask) });
+                }
+
+                /// Clears the flag of the event, so that its interrupt is no longer pending
+                ///
+                /// Panics, if the timer does not support the event
+                pub fn clear_interrupt(&mut self, event: Event) {
+                    let mask = Self::supported_mask(event);
+
+                    // The flags are cleared by writing zero, writing one has no effect,
+                    // so all other flags, including the overcapture flags, are kept
+                    self.tim.sr.write(|w| unsafe { w.bits(0xFFFF & !mask) });
+                }
+
+                /// Returns true, if the event has occurred since its flag was cleared
+                ///
+                /// Panics, if the timer does not support the event
+                pub fn is_pending(&self, event: Event) -> bool {
+                    let mask = Self::supported_mask(event);
+
+                    self.tim.sr.read().bits() & mask != 0
+                }
+
+                fn supported_mask(event: Event) -> u32 {
+                    let mask = event.mask();
+
+                    assert!(mask & $events != 0, "The event is not supported by this timer");
+
+                    mask
                 }
 
                 pub fn release(self) -> $TIMX {
@@ -114,16 +203,206 @@ macro_rules! timers {
 
 // TIM1 and TIM8 are advanced timers, and not supported
 timers! {
-    TIM2, tim2, tim2en, tim2rst, APB1, apb1;
-    TIM3, tim3, tim3en, tim3rst, APB1, apb1;
-    TIM4, tim4, tim4en, tim4rst, APB1, apb1;
-    TIM5, tim5, tim5en, tim5rst, APB1, apb1;
-    TIM6, tim6, tim6en, tim6rst, APB1, apb1;
-    TIM7, tim7, tim7en, tim7rst, APB1, apb1;
-    TIM9, tim9, tim9en, tim9rst, APB2, apb2;
-    TIM10, tim10, tim10en, tim10rst, APB2, apb2;
-    TIM11, tim11, tim11en, tim11rst, APB2, apb2;
-    TIM12, tim12, tim12en, tim12rst, APB1, apb1;
-    TIM13, tim13, tim13en, tim13rst, APB1, apb1;
-    TIM14, tim14, tim14en, tim14rst, APB1, apb1;
+    TIM2, tim2, tim2en, tim2rst, APB1, apb1, timclk1, EVENTS_4_CHANNELS;
+    TIM3, tim3, tim3en, tim3rst, APB1, apb1, timclk1, EVENTS_4_CHANNELS;
+    TIM4, tim4, tim4en, tim4rst, APB1, apb1, timclk1, EVENTS_4_CHANNELS;
+    TIM5, tim5, tim5en, tim5rst, APB1, apb1, timclk1, EVENTS_4_CHANNELS;
+    TIM6, tim6, tim6en, tim6rst, APB1, apb1, timclk1, EVENTS_BASIC;
+    TIM7, tim7, tim7en, tim7rst, APB1, apb1, timclk1, EVENTS_BASIC;
+    TIM9, tim9, tim9en, tim9rst, APB2, apb2, timclk2, EVENTS_2_CHANNELS;
+    TIM10, tim10, tim10en, tim10rst, APB2, apb2, timclk2, EVENTS_1_CHANNEL;
+    TIM11, tim11, tim11en, tim11rst, APB2, apb2, timclk2, EVENTS_1_CHANNEL;
+    TIM12, tim12, tim12en, tim12rst, APB1, apb1, timclk1, EVENTS_2_CHANNELS;
+    TIM13, tim13, tim13en, tim13rst, APB1, apb1, timclk1, EVENTS_1_CHANNEL;
+    TIM14, tim14, tim14en, tim14rst, APB1, apb1, timclk1, EVENTS_1_CHANNEL;
+}
+
+macro_rules! one_pulse_timers {
+    ($($TIMX:ident,)+) => {
+        $(
+            impl Timer<$TIMX> {
+                /// Enables or disables the one-pulse mode
+                ///
+                /// In one-pulse mode, the timer stops counting after the next time out,
+                /// instead of restarting the count down.
+                /// It can be started again with `start`.
+                pub fn set_one_pulse_mode(&mut self, one_pulse: bool) {
+                    self.tim.cr1.modify(|_, w| w.opm().bit(one_pulse));
+                }
+            }
+        )+
+    };
+}
+
+// TIM10, TIM11, TIM13 and TIM14 have no one-pulse mode
+one_pulse_timers! {
+    TIM2, TIM3, TIM4, TIM5, TIM6, TIM7, TIM9, TIM12,
+}
+
+/// Source of the trigger output (TRGO) of a master timer
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MasterMode {
+    /// The reset of the timer (UG bit or slave reset) is used as trigger output
+    Reset,
+    /// The counter enable signal is used as trigger output
+    Enable,
+    /// The update event (count down ended) is used as trigger output
+    Update,
+    /// A pulse is sent, when capture/compare channel 1 matches
+    ComparePulse,
+    /// The output compare reference signal of channel 1 is used as trigger output
+    Compare1,
+    /// The output compare reference signal of channel 2 is used as trigger output
+    Compare2,
+    /// The output compare reference signal of channel 3 is used as trigger output
+    Compare3,
+    /// The output compare reference signal of channel 4 is used as trigger output
+    Compare4,
+}
+
+impl MasterMode {
+    fn bits(self) -> u8 {
+        match self {
+            MasterMode::Reset => 0b000,
+            MasterMode::Enable => 0b001,
+            MasterMode::Update => 0b010,
+            MasterMode::ComparePulse => 0b011,
+            MasterMode::Compare1 => 0b100,
+            MasterMode::Compare2 => 0b101,
+            MasterMode::Compare3 => 0b110,
+            MasterMode::Compare4 => 0b111,
+        }
+    }
+}
+
+/// Reaction of a slave timer to the trigger output of its master
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SlaveMode {
+    /// The counter of the slave is reset on every trigger
+    Reset,
+    /// The slave only counts while the trigger is high
+    Gated,
+    /// The counter of the slave is started on the trigger
+    Trigger,
+    /// The slave counts the rising edges of the trigger,
+    /// e.g. to cascade two timers to a wider counter
+    ExternalClock,
+}
+
+impl SlaveMode {
+    fn bits(self) -> u8 {
+        match self {
+            SlaveMode::Reset => 0b100,
+            SlaveMode::Gated => 0b101,
+            SlaveMode::Trigger => 0b110,
+            SlaveMode::ExternalClock => 0b111,
+        }
+    }
+}
+
+/// A timer that can drive its trigger output (TRGO)
+pub trait MasterTimer: Sealed {
+    #[doc(hidden)]
+    fn set_master_mode(&mut self, mode: MasterMode);
+}
+
+/// A timer that can be controlled by the trigger output of another timer
+pub trait SlaveTimer: Sealed {
+    #[doc(hidden)]
+    fn set_slave_mode(&mut self, internal_trigger: u8, mode: SlaveMode);
+
+    #[doc(hidden)]
+    fn disable_slave_mode(&mut self);
+}
+
+/// Implemented by a slave timer for every master timer it is connected to
+///
+/// The connections are fixed in hardware, so invalid pairs do not compile.
+pub trait InternalTrigger<MASTER>: SlaveTimer {
+    /// Number of the internal trigger input (ITRx) connected to the master
+    const ITR: u8;
+}
+
+impl<MASTER: MasterTimer> Timer<MASTER> {
+    /// Selects the source of the trigger output
+    ///
+    /// The basic timers TIM6 and TIM7 only support `Reset`, `Enable` and `Update`.
+    pub fn set_master_mode(&mut self, mode: MasterMode) {
+        self.tim.set_master_mode(mode);
+    }
+
+    /// Connects the slave timer to the trigger output of this timer
+    pub fn set_slave<SLAVE>(&mut self, slave: &mut Timer<SLAVE>, mode: SlaveMode)
+    where
+        SLAVE: InternalTrigger<MASTER>,
+    {
+        slave.tim.set_slave_mode(SLAVE::ITR, mode);
+    }
+}
+
+impl<SLAVE: SlaveTimer> Timer<SLAVE> {
+    /// Disconnects the timer from its master, so that it counts on its own clock again
+    pub fn disable_slave_mode(&mut self) {
+        self.tim.disable_slave_mode();
+    }
+}
+
+macro_rules! master_timers {
+    ($($TIMX:ident,)+) => {
+        $(
+            impl MasterTimer for $TIMX {
+                // NOTE(allow) `w.mms().bits()` is safe for some timers but not for others because of
+                // some SVD omission
+                #[allow(unused_unsafe)]
+                fn set_master_mode(&mut self, mode: MasterMode) {
+                    self.cr2.modify(|_, w| unsafe { w.mms().bits(mode.bits()) });
+                }
+            }
+        )+
+    };
+}
+
+master_timers! {
+    TIM2, TIM3, TIM4, TIM5, TIM6, TIM7, TIM9, TIM12,
+}
+
+macro_rules! slave_timers {
+    ($($TIMX:ident: $($MASTER:ident => $itr:expr),+;)+) => {
+        $(
+            impl SlaveTimer for $TIMX {
+                // NOTE(allow) `w.ts().bits()` and `w.sms().bits()` are safe for some timers but not for others
+                // because of some SVD omission
+                #[allow(unused_unsafe)]
+                fn set_slave_mode(&mut self, internal_trigger: u8, mode: SlaveMode) {
+                    // The slave mode controller has to be disabled while the trigger is changed
+                    self.smcr.modify(|_, w| unsafe { w.sms().bits(0b000) });
+                    self.smcr.modify(|_, w| unsafe { w.ts().bits(internal_trigger) });
+                    self.smcr.modify(|_, w| unsafe { w.sms().bits(mode.bits()) });
+                }
+
+                // NOTE(allow) see above
+                #[allow(unused_unsafe)]
+                fn disable_slave_mode(&mut self) {
+                    self.smcr.modify(|_, w| unsafe { w.sms().bits(0b000) });
+                }
+            }
+
+            $(
+                impl InternalTrigger<$MASTER> for $TIMX {
+                    const ITR: u8 = $itr;
+                }
+            )+
+        )+
+    };
+}
+
+// Internal trigger connections, see the reference manual (RM0033).
+// Connections to the advanced timers TIM1 and TIM8 are omitted, as they are not supported.
+slave_timers! {
+    TIM2: TIM3 => 2, TIM4 => 3;
+    TIM3: TIM2 => 1, TIM5 => 2, TIM4 => 3;
+    TIM4: TIM2 => 1, TIM3 => 2;
+    TIM5: TIM2 => 0, TIM3 => 1, TIM4 => 2;
+    TIM9: TIM2 => 0, TIM3 => 1;
+    TIM12: TIM4 => 0, TIM5 => 1;
 }
@@ -0,0 +1,212 @@
+//! Independent (IWDG) and window (WWDG) watchdogs
+
+use embedded_hal::watchdog::{Watchdog, WatchdogEnable};
+use stm32f2::stm32f217::{DBGMCU, IWDG, WWDG};
+
+use crate::{
+    rcc::{Clocks, APB1, LSI},
+    time::{Duration, Hertz},
+};
+
+/// The independent watchdog, clocked by the low speed internal oscillator (LSI)
+///
+/// Once started, it can not be stopped anymore, except by a reset.
+pub struct IndependentWatchdog {
+    iwdg: IWDG,
+}
+
+impl IndependentWatchdog {
+    /// Longest timeout that can be configured
+    pub const MAX_TIMEOUT: Duration = Duration::from_micros(0x1000 * 256 * 1_000_000 / LSI as u64);
+
+    pub fn new(iwdg: IWDG) -> Self {
+        Self { iwdg }
+    }
+
+    /// Freezes the watchdog while the core is halted by the debugger
+    pub fn stop_in_debug(&mut self, dbgmcu: &mut DBGMCU, stop: bool) {
+        dbgmcu.apb1_fz.modify(|_, w| w.dbg_iwdg_stop().bit(stop));
+    }
+
+    /// Finds the smallest prescaler, for which the reload value fits into 12 bits
+    ///
+    /// Returns the prescaler bits and the reload value
+    fn prescaler_and_reload(timeout: Duration) -> (u8, u16) {
+        for prescaler_bits in 0..=6u8 {
+            let divider = 4u64 << prescaler_bits;
+            let ticks = timeout.as_micros() * LSI as u64 / (divider * 1_000_000);
+
+            if ticks <= 0x1000 {
+                let reload = Ord::max(ticks, 1) - 1;
+                return (prescaler_bits, reload as u16);
+            }
+        }
+
+        panic!("The timeout of the watchdog is too long");
+    }
+
+    fn wait_for_update(&self) {
+        while {
+            let status = self.iwdg.sr.read();
+            status.pvu().bit_is_set() || status.rvu().bit_is_set()
+        } {
+            // Wait until the new values have been transferred to the LSI clock domain
+        }
+    }
+}
+
+impl WatchdogEnable for IndependentWatchdog {
+    type Time = Duration;
+
+    /// Starts the watchdog with the given timeout
+    ///
+    /// Panics, if the timeout is longer than `MAX_TIMEOUT`.
+    fn start<T>(&mut self, period: T)
+    where
+        T: Into<Self::Time>,
+    {
+        let (prescaler_bits, reload) = Self::prescaler_and_reload(period.into());
+
+        // Starting the watchdog also enables the LSI
+        self.iwdg.kr.write(|w| w.key().start());
+
+        // Unlock the configuration registers
+        self.iwdg.kr.write(|w| w.key().enable());
+        self.iwdg.pr.write(|w| w.pr().bits(prescaler_bits));
+        self.iwdg.rlr.write(|w| w.rl().bits(reload));
+        self.wait_for_update();
+
+        self.feed();
+    }
+}
+
+impl Watchdog for IndependentWatchdog {
+    fn feed(&mut self) {
+        self.iwdg.kr.write(|w| w.key().reset());
+    }
+}
+
+/// The window watchdog, clocked by the APB1
+///
+/// It resets the device, if it is not fed within the timeout,
+/// or if it is fed too early, before the window has opened.
+/// Once started, it can not be stopped anymore, except by a reset.
+pub struct WindowWatchdog {
+    wwdg: WWDG,
+    pclk1: Hertz,
+    // Value the counter is reloaded with, when the watchdog is fed
+    counter: u8,
+    window: Option<Duration>,
+}
+
+impl WindowWatchdog {
+    // The watchdog resets the device, when the counter falls below this value
+    const MIN_COUNTER: u8 = 0x40;
+    const MAX_COUNTER: u8 = 0x7F;
+
+    pub fn new(wwdg: WWDG, clocks: Clocks, apb1: &mut APB1) -> Self {
+        apb1.enr().modify(|_, w| w.wwdgen().set_bit());
+
+        Self {
+            wwdg,
+            pclk1: clocks.pclk1(),
+            counter: Self::MAX_COUNTER,
+            window: None,
+        }
+    }
+
+    /// Sets the time after feeding, during which the watchdog must not be fed again
+    ///
+    /// Takes effect, when the watchdog is started.
+    pub fn set_window(&mut self, window: Duration) {
+        self.window = Some(window);
+    }
+
+    /// Freezes the watchdog while the core is halted by the debugger
+    pub fn stop_in_debug(&mut self, dbgmcu: &mut DBGMCU, stop: bool) {
+        dbgmcu.apb1_fz.modify(|_, w| w.dbg_wwdg_stop().bit(stop));
+    }
+
+    /// Enables the early wakeup interrupt
+    ///
+    /// The interrupt is triggered one counter tick before the watchdog resets the device.
+    /// It can not be disabled anymore, except by a reset.
+    pub fn listen(&mut self) {
+        self.wwdg.cfr.modify(|_, w| w.ewi().set_bit());
+    }
+
+    /// Returns true, if the early wakeup interrupt is pending
+    pub fn is_pending(&self) -> bool {
+        self.wwdg.sr.read().ewif().bit_is_set()
+    }
+
+    pub fn clear_interrupt(&mut self) {
+        self.wwdg.sr.write(|w| w.ewif().clear_bit());
+    }
+
+    /// Returns the number of APB1 cycles per counter tick, for the timer base bits
+    fn cycles_per_tick(timer_base_bits: u8) -> u64 {
+        4096 << timer_base_bits
+    }
+
+    /// Converts the duration into counter ticks, rounded up
+    fn ticks(&self, duration: Duration, timer_base_bits: u8) -> u64 {
+        let cycles = duration.cycles(self.pclk1);
+
+        cycles.div_ceil(Self::cycles_per_tick(timer_base_bits))
+    }
+}
+
+impl WatchdogEnable for WindowWatchdog {
+    type Time = Duration;
+
+    /// Starts the watchdog with the given timeout
+    ///
+    /// Panics, if the timeout is too long for the APB1 clock,
+    /// or if the window is longer than the timeout.
+    fn start<T>(&mut self, period: T)
+    where
+        T: Into<Self::Time>,
+    {
+        const MAX_TICKS: u64 =
+            (WindowWatchdog::MAX_COUNTER - WindowWatchdog::MIN_COUNTER + 1) as u64;
+
+        let timeout = period.into();
+
+        // Find the smallest timer base, for which the timeout fits into the counter
+        let (timer_base_bits, ticks) = (0..=3u8)
+            .map(|timer_base_bits| (timer_base_bits, self.ticks(timeout, timer_base_bits)))
+            .find(|(_, ticks)| *ticks <= MAX_TICKS)
+            .expect("The timeout of the watchdog is too long");
+
+        self.counter = Self::MIN_COUNTER - 1 + Ord::max(ticks, 1) as u8;
+
+        let window = match self.window {
+            Some(window) => {
+                let window_ticks = self.ticks(window, timer_base_bits);
+                assert!(
+                    window_ticks < ticks,
+                    "The window of the watchdog must be shorter than the timeout"
+                );
+                self.counter - window_ticks as u8
+            }
+            None => Self::MAX_COUNTER,
+        };
+
+        self.wwdg
+            .cfr
+            .modify(|_, w| w.wdgtb().bits(timer_base_bits).w().bits(window));
+
+        self.wwdg
+            .cr
+            .write(|w| w.wdga().set_bit().t().bits(self.counter));
+    }
+}
+
+impl Watchdog for WindowWatchdog {
+    fn feed(&mut self) {
+        self.wwdg
+            .cr
+            .write(|w| w.wdga().set_bit().t().bits(self.counter));
+    }
+}